@@ -0,0 +1,229 @@
+///Real and complex cepstra through the fft, liftering and minimum-phase reconstruction
+extern crate num;
+
+use self::num::complex;
+
+use error;
+use fft;
+use fft::FftFloat;
+use utility;
+
+#[cfg(test)]
+use test_common::{ nearly_equal, noise };
+
+///quefrency window applied to a cepstrum, cutoffs are in samples and mirrored into the upper half
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Lifter {
+    ///keeps quefrencies below the cutoff, the smooth spectral envelope
+    LowTime( usize ),
+    ///keeps quefrencies from the cutoff up, the excitation, echoes and rahmonics
+    HighTime( usize ),
+    ///1 + L/2 * sin(pi*n/L) for n < L and zero beyond, the mfcc cepstral weighting
+    Sinusoidal( usize ),
+}
+
+///quefrency in seconds of each of the n cepstral samples at sample rate fs
+pub fn quefrencies<T: FftFloat>( n: usize, fs: T ) -> Vec<T> {
+    (0..n).map( |k| T::from( k ).unwrap() / fs ).collect()
+}
+
+///ifft( ln|fft(x)| ), errors with DataInvalid if a bin has zero magnitude
+pub fn real_cepstrum<T: FftFloat>( x: &[T] ) -> Result< Vec<T>, error::Error > {
+    if x.is_empty() {
+        return Err( error::Error::DataEmpty )
+    }
+    let log_mag = fft::fft_dif( x ).iter().map( |v| {
+        let m = v.norm();
+        if m > T::zero() { Ok( complex::Complex::new( m.ln(), T::zero() ) ) } else { Err( error::Error::DataInvalid ) }
+    }).collect::<Result< Vec<_>, _ >>()?;
+    Ok( fft::ifft_dif( &log_mag[..] ) )
+}
+
+///complex cepstrum with the parts of the phase a real ifft cannot hold
+#[derive(Debug, Clone, PartialEq)]
+pub struct ComplexCepstrum<T: FftFloat> {
+    pub cepstrum: Vec<T>,
+    ///linear phase removed before the ifft, as a circular delay in samples
+    pub delay: isize,
+    ///sign of the dc bin, the cepstrum is of sign * x
+    pub sign: T,
+}
+
+///ifft( ln|X| + i*arg(X) ) with the phase unwrapped and its linear trend and dc sign removed.
+///errors with DataInvalid if a bin has zero magnitude
+pub fn complex_cepstrum<T: FftFloat>( x: &[T] ) -> Result< ComplexCepstrum<T>, error::Error > {
+    if x.is_empty() {
+        return Err( error::Error::DataEmpty )
+    }
+    let mut spectrum = fft::fft_dif( x );
+    if spectrum.iter().any( |v| v.norm_sqr() == T::zero() ) {
+        return Err( error::Error::DataInvalid )
+    }
+    //a negative dc bin would leave pi of phase at dc that the real part of the ifft drops
+    let sign = if spectrum[0].re < T::zero() { -T::one() } else { T::one() };
+    spectrum.iter_mut().for_each( |v| *v *= sign );
+    let n = spectrum.len();
+    //unwrapping only up to nyquist and mirroring keeps the log spectrum exactly conjugate symmetric,
+    //the two halves could otherwise unwrap differently where adjacent bins differ by close to pi
+    let k_ref = n / 2;
+    let phase = utility::unwrap_phase( &spectrum[..=k_ref].iter().map( |v| v.arg() ).collect::<Vec<_>>()[..] );
+    //the last unwrapped phase over -2pi*k_ref/n rounds to the linear phase delay, removed as a whole sample circular shift
+    let delay = if k_ref > 0 {
+        ( -phase[k_ref] * T::from( n ).unwrap() / ( T::PI() * T::from( 2 * k_ref ).unwrap() ) ).round().to_isize().unwrap_or( 0 )
+    } else {
+        0
+    };
+    let log_spectrum = spectrum.iter().enumerate().map( |(k, v)| {
+        let p = if k <= k_ref {
+            phase[k] + linear_phase::<T>( delay, k, n )
+        } else {
+            -( phase[ n - k ] + linear_phase::<T>( delay, n - k, n ) )
+        };
+        complex::Complex::new( v.norm().ln(), p )
+    }).collect::<Vec<_>>();
+    Ok( ComplexCepstrum { cepstrum: fft::ifft_dif( &log_spectrum[..] ), delay, sign } )
+}
+
+///signal a complex cepstrum was computed from
+pub fn inverse_complex_cepstrum<T: FftFloat>( c: &ComplexCepstrum<T> ) -> Vec<T> {
+    let n = c.cepstrum.len();
+    let spectrum = fft::fft_dif( &c.cepstrum[..] ).iter().enumerate().map( |(k, v)| {
+        complex::Complex::from_polar( &v.re.exp(), &( v.im - linear_phase::<T>( c.delay, k, n ) ) )
+    }).collect::<Vec<_>>();
+    fft::ifft_dif( &spectrum[..] ).iter().map( |x| *x * c.sign ).collect()
+}
+
+///2pi*delay*k/n, the phase a delay of whole samples subtracts from bin k
+fn linear_phase<T: FftFloat>( delay: isize, k: usize, n: usize ) -> T {
+    ( T::PI() + T::PI() ) * T::from( delay ).unwrap() * T::from( k ).unwrap() / T::from( n ).unwrap()
+}
+
+///cepstrum weighted by lifter at quefrencies n and their mirrors len - n
+pub fn lifter<T: FftFloat>( cepstrum: &[T], lifter: Lifter ) -> Vec<T> {
+    let len = cepstrum.len();
+    cepstrum.iter().enumerate().map( |(i, c)| {
+        let q = i.min( len - i );
+        let w = match lifter {
+            Lifter::LowTime( cutoff ) => if q < cutoff { T::one() } else { T::zero() },
+            Lifter::HighTime( cutoff ) => if q < cutoff { T::zero() } else { T::one() },
+            Lifter::Sinusoidal( l ) => {
+                if q < l {
+                    let l = T::from( l ).unwrap();
+                    T::one() + l / ( T::one() + T::one() ) * ( T::PI() * T::from( q ).unwrap() / l ).sin()
+                } else {
+                    T::zero()
+                }
+            },
+        };
+        *c * w
+    }).collect()
+}
+
+///minimum-phase signal with the magnitude spectrum a real cepstrum describes, by folding the
+///cepstrum onto positive quefrencies. the cepstrum should be long enough that its tail has decayed
+pub fn minimum_phase_from_cepstrum<T: FftFloat>( cepstrum: &[T] ) -> Vec<T> {
+    let n = cepstrum.len();
+    let two = T::one() + T::one();
+    let folded = cepstrum.iter().enumerate().map( |(i, c)| {
+        if i == 0 || 2 * i == n {
+            *c
+        } else if 2 * i < n {
+            *c * two
+        } else {
+            T::zero()
+        }
+    }).collect::<Vec<_>>();
+    let spectrum = fft::fft_dif( &folded[..] ).iter().map( |v| {
+        complex::Complex::from_polar( &v.re.exp(), &v.im )
+    }).collect::<Vec<_>>();
+    fft::ifft_dif( &spectrum[..] )
+}
+
+///minimum-phase signal with the same magnitude spectrum as x, errors as real_cepstrum
+pub fn minimum_phase<T: FftFloat>( x: &[T] ) -> Result< Vec<T>, error::Error > {
+    Ok( minimum_phase_from_cepstrum( &real_cepstrum( x )?[..] ) )
+}
+
+#[test]
+fn test_real_cepstrum_echo() {
+    //an echo at 37 samples puts a peak and its rahmonics at multiples of 37 quefrency
+    let ( n, d ) = ( 1024, 37 );
+    let s = noise( 512, 5 );
+    let mut x = vec![ 0f64; n ];
+    for (i, v) in s.iter().enumerate() {
+        x[i] += *v;
+        x[ i + d ] += 0.6 * *v;
+    }
+    let c = real_cepstrum( &x[..] ).unwrap();
+    let high = lifter( &c[..], Lifter::HighTime( 10 ) );
+    let peak = (0..n/2).max_by( |a, b| high[*a].partial_cmp( &high[*b] ).unwrap() ).unwrap();
+    assert_eq!( peak, d );
+    assert!( high[ 2 * d ] < 0. );
+    assert!( nearly_equal( &quefrencies( n, 1000. )[d], &0.037, &1e-12 ) );
+    assert!( real_cepstrum( &[ 0f64; 8 ] ).is_err() );
+    assert!( real_cepstrum::<f64>( &[] ).is_err() );
+}
+
+#[test]
+fn test_complex_cepstrum() {
+    //1 - a*z^-1 has complex cepstrum -a^k/k for k >= 1
+    let n = 64;
+    let a = 0.5;
+    let mut x = vec![ 0f64; n ];
+    x[0] = 1.;
+    x[1] = -a;
+    let c = complex_cepstrum( &x[..] ).unwrap();
+    assert_eq!( ( c.delay, c.sign ), ( 0, 1. ) );
+    assert!( nearly_equal( &c.cepstrum[0], &0., &1e-12 ) );
+    for (k, v) in c.cepstrum.iter().enumerate().take( 20 ).skip( 1 ) {
+        assert!( nearly_equal( v, &( -a.powi( k as i32 ) / k as f64 ), &1e-12 ) );
+    }
+
+    //mixed phase signals with a delay and either dc sign round trip
+    let mut signs = vec![];
+    for seed in 1..6 {
+        let mut x = vec![ 0f64; 128 ];
+        let s = noise( 32, seed );
+        x[ 3 .. 35 ].copy_from_slice( &s[..] );
+        let c = complex_cepstrum( &x[..] ).unwrap();
+        signs.push( c.sign );
+        let y = inverse_complex_cepstrum( &c );
+        x.iter().zip( y.iter() ).for_each( |v| assert!( nearly_equal( v.0, v.1, &1e-9 ) ) );
+    }
+    assert!( signs.contains( &1. ) && signs.contains( &-1. ) );
+}
+
+#[test]
+fn test_minimum_phase() {
+    //1 + 2z^-1 has its zero outside the unit circle, 2 + z^-1 has the same magnitude response
+    let mut x = vec![ 0f64; 64 ];
+    x[0] = 1.;
+    x[1] = 2.;
+    let y = minimum_phase( &x[..] ).unwrap();
+    assert!( nearly_equal( &y[0], &2., &1e-9 ) );
+    assert!( nearly_equal( &y[1], &1., &1e-9 ) );
+    y[2..].iter().for_each( |v| assert!( nearly_equal( v, &0., &1e-9 ) ) );
+
+    //magnitude is kept for longer responses
+    let mut x = vec![ 0f64; 256 ];
+    x[..16].copy_from_slice( &noise( 16, 9 )[..] );
+    let y = minimum_phase( &x[..] ).unwrap();
+    let mx = fft::fft_dif( &x[..] );
+    let my = fft::fft_dif( &y[..] );
+    mx.iter().zip( my.iter() ).for_each( |v| assert!( nearly_equal( &v.0.norm(), &v.1.norm(), &1e-6 ) ) );
+    //minimum phase concentrates energy at the start
+    let energy = |v: &[f64]| v.iter().map( |s| s * s ).sum::<f64>();
+    assert!( energy( &y[..4] ) >= energy( &x[..4] ) );
+}
+
+#[test]
+fn test_lifter() {
+    let c = [ 1f64; 8 ];
+    assert_eq!( lifter( &c[..], Lifter::LowTime( 2 ) ), vec![ 1., 1., 0., 0., 0., 0., 0., 1. ] );
+    assert_eq!( lifter( &c[..], Lifter::HighTime( 2 ) ), vec![ 0., 0., 1., 1., 1., 1., 1., 0. ] );
+    let s = lifter( &c[..], Lifter::Sinusoidal( 4 ) );
+    assert!( nearly_equal( &s[0], &1., &1e-12 ) );
+    assert!( nearly_equal( &s[2], &3., &1e-12 ) );
+    assert!( nearly_equal( &s[6], &3., &1e-12 ) );
+    assert_eq!( s[4], 0. );
+}
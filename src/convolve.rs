@@ -0,0 +1,298 @@
+///Linear convolution, direct and through the radix-2 fft, with streaming block convolvers
+extern crate num;
+
+use self::num::complex;
+
+use error;
+use fft;
+use fft::{ Direction, FftFloat, FftPlan };
+
+#[cfg(test)]
+use test_common::{ test_signal, assert_slice_eq };
+
+///output region of a linear convolution of lengths na and nb
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Mode {
+    ///all na+nb-1 samples
+    Full,
+    ///na samples centered on the full output
+    Same,
+    ///only samples where the inputs fully overlap, |na-nb|+1 samples
+    Valid,
+}
+
+fn next_power2( n: usize ) -> usize {
+    let mut i = 1;
+    while i < n {
+        i <<= 1;
+    }
+    i
+}
+
+fn crop<T: Clone>( full: Vec<T>, na: usize, nb: usize, mode: Mode ) -> Vec<T> {
+    match mode {
+        Mode::Full => full,
+        Mode::Same => {
+            let start = ( nb - 1 ) / 2;
+            full[ start .. start + na ].to_vec()
+        },
+        Mode::Valid => {
+            let start = na.min( nb ) - 1;
+            let len = na.max( nb ) - na.min( nb ) + 1;
+            full[ start .. start + len ].to_vec()
+        },
+    }
+}
+
+///direct O(na*nb) convolution
+pub fn convolve<T: FftFloat>( a: &[T], b: &[T], mode: Mode ) -> Vec<T> {
+    if a.is_empty() || b.is_empty() {
+        return vec![]
+    }
+    let mut full = vec![ T::zero(); a.len() + b.len() - 1 ];
+    for (i, x) in a.iter().enumerate() {
+        for (j, y) in b.iter().enumerate() {
+            full[ i + j ] += *x * *y;
+        }
+    }
+    crop( full, a.len(), b.len(), mode )
+}
+
+///convolution as a product of real spectra zero padded to a power of 2
+pub fn fftconvolve<T: FftFloat>( a: &[T], b: &[T], mode: Mode ) -> Vec<T> {
+    if a.is_empty() || b.is_empty() {
+        return vec![]
+    }
+    let len = a.len() + b.len() - 1;
+    let n = next_power2( len );
+    let mut pa = a.to_vec();
+    pa.resize( n, T::zero() );
+    let mut pb = b.to_vec();
+    pb.resize( n, T::zero() );
+    let fa = fft::rfft( &pa[..] );
+    let fb = fft::rfft( &pb[..] );
+    let prod = fa.iter().zip( fb.iter() ).map( |(x, y)| x * y ).collect::<Vec<_>>();
+    let mut full = fft::irfft( &prod[..], n ).expect("spectrum length mismatch");
+    full.truncate( len );
+    crop( full, a.len(), b.len(), mode )
+}
+
+///precomputed kernel spectrum and plans shared by the block convolvers
+#[derive(Debug, Clone)]
+struct BlockKernel<T: FftFloat> {
+    kernel_len: usize,
+    kernel_freq: Vec<complex::Complex<T>>,
+    forward: FftPlan<T>,
+    inverse: FftPlan<T>,
+}
+
+impl<T: FftFloat> BlockKernel<T> {
+    fn new( kernel: &[T], fft_len: usize ) -> BlockKernel<T> {
+        let forward = FftPlan::new( fft_len, Direction::Forward );
+        let mut kernel_freq = vec![ complex::Complex::new( T::zero(), T::zero() ); fft_len ];
+        for (dst, x) in kernel_freq.iter_mut().zip( kernel.iter() ) {
+            dst.re = *x;
+        }
+        forward.process( &mut kernel_freq[..] ).expect("plan length mismatch");
+        BlockKernel {
+            kernel_len: kernel.len(),
+            kernel_freq,
+            forward,
+            inverse: FftPlan::new( fft_len, Direction::Inverse ),
+        }
+    }
+
+    ///circular convolution of buf with the kernel, returns the real part
+    fn filter( &self, buf: &[T] ) -> Vec<T> {
+        let n = self.kernel_freq.len();
+        let mut v = vec![ complex::Complex::new( T::zero(), T::zero() ); n ];
+        for (dst, x) in v.iter_mut().zip( buf.iter() ) {
+            dst.re = *x;
+        }
+        self.forward.process( &mut v[..] ).expect("plan length mismatch");
+        for (x, h) in v.iter_mut().zip( self.kernel_freq.iter() ) {
+            *x *= *h;
+        }
+        self.inverse.process( &mut v[..] ).expect("plan length mismatch");
+        let scale = T::one() / T::from( n ).unwrap();
+        v.iter().map( |x| x.re * scale ).collect()
+    }
+}
+
+///streaming overlap-add convolver.
+///input of any length is buffered into blocks, each block's output tail is added into the next
+#[derive(Debug, Clone)]
+pub struct OverlapAdd<T: FftFloat> {
+    kernel: BlockKernel<T>,
+    block_len: usize,
+    pending: Vec<T>,
+    tail: Vec<T>,
+}
+
+impl<T: FftFloat> OverlapAdd<T> {
+
+    pub fn new( kernel: &[T], block_len: usize ) -> Result< OverlapAdd<T>, error::Error > {
+        if kernel.is_empty() {
+            return Err( error::Error::DataEmpty )
+        }
+        if block_len == 0 {
+            return Err( error::Error::DataInvalid )
+        }
+        let fft_len = next_power2( block_len + kernel.len() - 1 );
+        Ok( OverlapAdd {
+            kernel: BlockKernel::new( kernel, fft_len ),
+            block_len,
+            pending: vec![],
+            tail: vec![ T::zero(); kernel.len() - 1 ],
+        })
+    }
+
+    fn run_block( &mut self, block: &[T] ) -> Vec<T> {
+        let y = self.kernel.filter( block );
+        let k = self.kernel.kernel_len - 1;
+        let mut out = y[ .. self.block_len + k ].to_vec();
+        for (o, t) in out.iter_mut().zip( self.tail.iter() ) {
+            *o += *t;
+        }
+        self.tail = out.split_off( block.len() );
+        self.tail.truncate( k );
+        out
+    }
+
+    ///returns output for every completed block, one sample per input sample consumed
+    pub fn process( &mut self, input: &[T] ) -> Vec<T> {
+        self.pending.extend_from_slice( input );
+        let mut ret = vec![];
+        while self.pending.len() >= self.block_len {
+            let block = self.pending.drain( .. self.block_len ).collect::<Vec<_>>();
+            ret.extend( self.run_block( &block[..] ) );
+        }
+        ret
+    }
+
+    ///processes any partial block and returns the remaining kernel_len-1 samples of the full convolution
+    pub fn flush( &mut self ) -> Vec<T> {
+        let block = self.pending.drain( .. ).collect::<Vec<_>>();
+        let mut ret = self.run_block( &block[..] );
+        ret.append( &mut self.tail );
+        self.tail = vec![ T::zero(); self.kernel.kernel_len - 1 ];
+        ret
+    }
+}
+
+///streaming overlap-save convolver.
+///each transform covers the previous kernel_len-1 inputs plus block_len new ones and keeps the uncorrupted part
+#[derive(Debug, Clone)]
+pub struct OverlapSave<T: FftFloat> {
+    kernel: BlockKernel<T>,
+    block_len: usize,
+    pending: Vec<T>,
+    history: Vec<T>,
+}
+
+impl<T: FftFloat> OverlapSave<T> {
+
+    pub fn new( kernel: &[T], block_len: usize ) -> Result< OverlapSave<T>, error::Error > {
+        if kernel.is_empty() {
+            return Err( error::Error::DataEmpty )
+        }
+        if block_len == 0 {
+            return Err( error::Error::DataInvalid )
+        }
+        let fft_len = next_power2( block_len + kernel.len() - 1 );
+        Ok( OverlapSave {
+            kernel: BlockKernel::new( kernel, fft_len ),
+            block_len,
+            pending: vec![],
+            history: vec![ T::zero(); kernel.len() - 1 ],
+        })
+    }
+
+    fn run_block( &mut self, block: &[T] ) -> Vec<T> {
+        let k = self.kernel.kernel_len - 1;
+        let mut buf = self.history.clone();
+        buf.extend_from_slice( block );
+        let y = self.kernel.filter( &buf[..] );
+        self.history = buf[ buf.len() - k .. ].to_vec();
+        //the first k outputs wrap around the circular convolution
+        y[ k .. k + block.len() ].to_vec()
+    }
+
+    ///returns output for every completed block, one sample per input sample consumed
+    pub fn process( &mut self, input: &[T] ) -> Vec<T> {
+        self.pending.extend_from_slice( input );
+        let mut ret = vec![];
+        while self.pending.len() >= self.block_len {
+            let block = self.pending.drain( .. self.block_len ).collect::<Vec<_>>();
+            ret.extend( self.run_block( &block[..] ) );
+        }
+        ret
+    }
+
+    ///processes any partial block and returns the remaining kernel_len-1 samples of the full convolution
+    pub fn flush( &mut self ) -> Vec<T> {
+        let mut block = self.pending.drain( .. ).collect::<Vec<_>>();
+        block.resize( block.len() + self.kernel.kernel_len - 1, T::zero() );
+        let mut ret = vec![];
+        for chunk in block.chunks( self.block_len ) {
+            ret.extend( self.run_block( chunk ) );
+        }
+        self.history = vec![ T::zero(); self.kernel.kernel_len - 1 ];
+        ret
+    }
+}
+
+#[test]
+fn test_convolve_modes() {
+    let a = [ 1., 2., 3., 4. ];
+    let b = [ 1., 0., -1. ];
+    assert_slice_eq( &convolve( &a, &b, Mode::Full ), &[ 1., 2., 2., 2., -3., -4. ], 1e-12 );
+    assert_slice_eq( &convolve( &a, &b, Mode::Same ), &[ 2., 2., 2., -3. ], 1e-12 );
+    assert_slice_eq( &convolve( &a, &b, Mode::Valid ), &[ 2., 2. ], 1e-12 );
+    assert_slice_eq( &convolve( &b, &a, Mode::Valid ), &[ 2., 2. ], 1e-12 );
+    assert!( convolve::<f64>( &a, &[], Mode::Full ).is_empty() );
+}
+
+#[test]
+fn test_fftconvolve() {
+    for &( na, nb ) in [ ( 1, 1 ), ( 4, 3 ), ( 100, 33 ), ( 17, 64 ), ( 1000, 257 ) ].iter() {
+        let a = test_signal::<f64>( na );
+        let b = test_signal::<f64>( nb + 5 )[5..].to_vec();
+        for &mode in [ Mode::Full, Mode::Same, Mode::Valid ].iter() {
+            assert_slice_eq( &fftconvolve( &a, &b, mode ), &convolve( &a, &b, mode ), 1e-9 );
+        }
+    }
+}
+
+#[test]
+fn test_overlap_add_save_streaming() {
+    let signal = test_signal::<f64>( 5000 );
+    let kernel = test_signal::<f64>( 300 ).iter().map( |x| x * 0.01 ).collect::<Vec<_>>();
+    let expected = convolve( &signal, &kernel, Mode::Full );
+    let mut ola = OverlapAdd::new( &kernel, 256 ).expect("overlap-add init failed");
+    let mut ols = OverlapSave::new( &kernel, 256 ).expect("overlap-save init failed");
+    let mut out_ola = vec![];
+    let mut out_ols = vec![];
+    //irregular chunk sizes exercise the internal buffering
+    let mut start = 0;
+    let mut step = 1;
+    while start < signal.len() {
+        let end = ( start + step ).min( signal.len() );
+        out_ola.extend( ola.process( &signal[ start .. end ] ) );
+        out_ols.extend( ols.process( &signal[ start .. end ] ) );
+        assert_eq!( out_ola.len(), end / 256 * 256 );
+        assert_eq!( out_ols.len(), end / 256 * 256 );
+        start = end;
+        step = step * 3 % 700 + 1;
+    }
+    out_ola.extend( ola.flush() );
+    out_ols.extend( ols.flush() );
+    assert_slice_eq( &out_ola, &expected, 1e-9 );
+    assert_slice_eq( &out_ols, &expected, 1e-9 );
+}
+
+#[test]
+fn test_block_convolver_invalid() {
+    assert!( OverlapAdd::<f64>::new( &[], 16 ).is_err() );
+    assert!( OverlapSave::new( &[ 1f32 ], 0 ).is_err() );
+}
@@ -0,0 +1,196 @@
+///Cross-correlation, autocorrelation and GCC-PHAT time delay estimation through the fft
+use error;
+use fft;
+use fft::FftFloat;
+use convolve;
+
+#[cfg(test)]
+use test_common::{ nearly_equal, noise };
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Scale {
+    None,
+    ///divide by the longer input length
+    Biased,
+    ///divide by the number of overlapping samples at each lag
+    Unbiased,
+    ///divide by sqrt(sum a^2 * sum b^2), 1 at lag 0 for autocorrelation
+    Normalized,
+}
+
+///lags of xcorr output, -(nb-1) ..= na-1
+pub fn lags( na: usize, nb: usize ) -> Vec<isize> {
+    if na == 0 || nb == 0 {
+        return vec![]
+    }
+    ( -( nb as isize - 1 ) .. na as isize ).collect()
+}
+
+///r(l) = sum_n a(n+l) * b(n) for every lag in lags(a.len(), b.len())
+pub fn xcorr<T: FftFloat>( a: &[T], b: &[T], scale: Scale ) -> Vec<T> {
+    if a.is_empty() || b.is_empty() {
+        return vec![]
+    }
+    let b_rev = b.iter().rev().cloned().collect::<Vec<_>>();
+    let r = convolve::fftconvolve( a, &b_rev[..], convolve::Mode::Full );
+    let ( na, nb ) = ( a.len() as isize, b.len() as isize );
+    match scale {
+        Scale::None => r,
+        Scale::Biased => {
+            let n = T::from( na.max( nb ) ).unwrap();
+            r.iter().map( |x| *x / n ).collect()
+        },
+        Scale::Unbiased => {
+            r.iter().zip( lags( a.len(), b.len() ).iter() ).map( |(x, &l)| {
+                let overlap = nb.min( na - l ) - 0.max( -l );
+                *x / T::from( overlap ).unwrap()
+            }).collect()
+        },
+        Scale::Normalized => {
+            let ea = a.iter().fold( T::zero(), |acc, x| acc + *x * *x );
+            let eb = b.iter().fold( T::zero(), |acc, x| acc + *x * *x );
+            let norm = ( ea * eb ).sqrt();
+            if norm > T::zero() {
+                r.iter().map( |x| *x / norm ).collect()
+            } else {
+                r
+            }
+        },
+    }
+}
+
+///autocorrelation over lags -(n-1) ..= n-1
+pub fn autocorr<T: FftFloat>( x: &[T], scale: Scale ) -> Vec<T> {
+    xcorr( x, x, scale )
+}
+
+///vertex offset in (-0.5,0.5) of the parabola through three samples around a peak
+fn parabolic_offset<T: FftFloat>( left: T, center: T, right: T ) -> T {
+    let den = left - center - center + right;
+    if den == T::zero() {
+        T::zero()
+    } else {
+        let half = T::from( 0.5 ).unwrap();
+        ( half * ( left - right ) / den ).max( -half ).min( half )
+    }
+}
+
+///generalized cross-correlation with phase transform.
+///returns the delay in seconds of sig relative to refsig, positive when sig lags,
+///searched within +/-max_tau seconds if given and refined to a fraction of a sample
+pub fn gcc_phat<T: FftFloat>( sig: &[T], refsig: &[T], fs: T, max_tau: Option<T> ) -> Result< T, error::Error > {
+    if sig.is_empty() || refsig.is_empty() {
+        return Err( error::Error::DataEmpty )
+    }
+    if fs <= T::zero() {
+        return Err( error::Error::DataInvalid )
+    }
+    let mut n = 1;
+    while n < sig.len() + refsig.len() {
+        n <<= 1;
+    }
+    let mut a = sig.to_vec();
+    a.resize( n, T::zero() );
+    let mut b = refsig.to_vec();
+    b.resize( n, T::zero() );
+    let fa = fft::rfft( &a[..] );
+    let fb = fft::rfft( &b[..] );
+    //whiten the cross spectrum so only phase remains
+    let cross = fa.iter().zip( fb.iter() ).map( |(x, y)| {
+        let r = x * y.conj();
+        let mag = r.norm();
+        if mag > T::epsilon() { r / mag } else { r * T::zero() }
+    }).collect::<Vec<_>>();
+    let cc = fft::irfft( &cross[..], n )?;
+
+    let mut max_shift = n / 2;
+    if let Some(tau) = max_tau {
+        let shift = ( tau * fs ).abs().floor().to_usize().unwrap_or( max_shift );
+        max_shift = max_shift.min( shift );
+    }
+    //circular lag l sits at index l for l >= 0 and n+l for l < 0
+    let at = |l: isize| -> T { cc[ ( l + n as isize ) as usize % n ] };
+    let mut best = 0isize;
+    for l in -( max_shift as isize ) ..= max_shift as isize {
+        if at( l ) > at( best ) {
+            best = l;
+        }
+    }
+    let offset = parabolic_offset( at( best - 1 ), at( best ), at( best + 1 ) );
+    Ok( ( T::from( best ).unwrap() + offset ) / fs )
+}
+
+#[test]
+fn test_xcorr_direct() {
+    let a = noise( 37, 1 );
+    let b = noise( 20, 2 );
+    let l = lags( a.len(), b.len() );
+    assert_eq!( l.len(), 56 );
+    assert_eq!( l[0], -19 );
+    assert_eq!( l[55], 36 );
+    let r = xcorr( &a, &b, Scale::None );
+    let unbiased = xcorr( &a, &b, Scale::Unbiased );
+    let biased = xcorr( &a, &b, Scale::Biased );
+    for (i, &lag) in l.iter().enumerate() {
+        let mut acc = 0.;
+        let mut count = 0;
+        for n in 0..b.len() as isize {
+            if n + lag >= 0 && n + lag < a.len() as isize {
+                acc += a[ ( n + lag ) as usize ] * b[ n as usize ];
+                count += 1;
+            }
+        }
+        assert!( nearly_equal( &r[i], &acc, &1e-10 ) );
+        assert!( nearly_equal( &unbiased[i], &( acc / count as f64 ), &1e-10 ) );
+        assert!( nearly_equal( &biased[i], &( acc / 37. ), &1e-10 ) );
+    }
+}
+
+#[test]
+fn test_autocorr() {
+    let x = noise( 64, 5 );
+    let r = autocorr( &x, Scale::Normalized );
+    assert_eq!( r.len(), 127 );
+    assert!( nearly_equal( &r[63], &1., &1e-12 ) );
+    assert!( r.iter().all( |v| v.abs() <= 1. + 1e-12 ) );
+    for k in 0..63 {
+        assert!( nearly_equal( &r[k], &r[126 - k], &1e-12 ) );
+    }
+    let biased = autocorr( &[ 1., 2., 3. ], Scale::Biased );
+    let expected = [ 1., 8. / 3., 14. / 3., 8. / 3., 1. ];
+    biased.iter().zip( expected.iter() ).for_each( |x| assert!( nearly_equal( x.0, x.1, &1e-12 ) ) );
+}
+
+#[test]
+fn test_gcc_phat_integer_delay() {
+    let fs = 16000.;
+    let src = noise( 2048, 9 );
+    let delay = 7;
+    let mut sig = vec![ 0.; delay ];
+    sig.extend_from_slice( &src[ .. src.len() - delay ] );
+    let tau = gcc_phat( &sig, &src, fs, None ).expect("gcc-phat failed");
+    assert!( nearly_equal( &( tau * fs ), &7., &1e-2 ) );
+    let tau = gcc_phat( &src, &sig, fs, Some( 0.001 ) ).expect("gcc-phat failed");
+    assert!( nearly_equal( &( tau * fs ), &-7., &1e-2 ) );
+    //delay outside the search window is not found
+    let tau = gcc_phat( &sig, &src, fs, Some( 3. / fs ) ).expect("gcc-phat failed");
+    assert!( ( tau * fs ).abs() <= 3.5 );
+}
+
+#[test]
+fn test_gcc_phat_fractional_delay() {
+    //circularly delay white noise by a fractional amount in the frequency domain
+    extern crate num;
+    let n = 4096;
+    let fs = 48000.;
+    let src = noise( n, 11 );
+    for &delay in [ 3.4, -12.75, 0.5 ].iter() {
+        let spec = fft::rfft( &src[..] ).iter().enumerate().map( |(k, x)| {
+            let phase = -2. * ::std::f64::consts::PI * k as f64 * delay / n as f64;
+            x * num::complex::Complex::from_polar( &1., &phase )
+        }).collect::<Vec<_>>();
+        let sig = fft::irfft( &spec[..], n ).expect("irfft failed");
+        let tau = gcc_phat( &sig, &src, fs, Some( 0.001 ) ).expect("gcc-phat failed");
+        assert!( nearly_equal( &( tau * fs ), &delay, &0.15 ), "{} {}", delay, tau * fs );
+    }
+}
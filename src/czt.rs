@@ -0,0 +1,151 @@
+///Chirp-Z transform along spirals of the z-plane and zoom fft over a narrow band
+extern crate num;
+
+use self::num::complex;
+
+use error;
+use fft::{ Direction, FftFloat, FftPlan };
+
+#[cfg(test)]
+use test_common::{ nearly_equal, noise_complex };
+
+///w^p for real p in polar form, evaluated in f64
+fn complex_pow<T: FftFloat>( w: complex::Complex<f64>, p: f64 ) -> complex::Complex<T> {
+    let ( r, theta ) = w.to_polar();
+    let angle = ( theta * p ) % ( 2. * ::std::f64::consts::PI );
+    let mag = r.powf( p );
+    complex::Complex::new( T::from( mag * angle.cos() ).unwrap(), T::from( mag * angle.sin() ).unwrap() )
+}
+
+fn to_f64<T: FftFloat>( z: complex::Complex<T> ) -> complex::Complex<f64> {
+    complex::Complex::new( z.re.to_f64().unwrap(), z.im.to_f64().unwrap() )
+}
+
+///contour points z_k = a * w^(-k) for k < m
+pub fn czt_points<T: FftFloat>( m: usize, w: complex::Complex<T>, a: complex::Complex<T> ) -> Vec< complex::Complex<T> > {
+    let w = to_f64( w );
+    (0..m).map( |k| a * complex_pow::<T>( w, -( k as f64 ) ) ).collect()
+}
+
+///X_k = sum_n x_n * z_k^(-n) at z_k = a * w^(-k), k < m.
+///computed by Bluestein's substitution n*k = (n^2 + k^2 - (k-n)^2)/2 as a power of 2 convolution
+pub fn czt<T: FftFloat>( x: &[complex::Complex<T>], m: usize, w: complex::Complex<T>, a: complex::Complex<T> ) -> Result< Vec< complex::Complex<T> >, error::Error > {
+    let n = x.len();
+    if n == 0 || m == 0 {
+        return Err( error::Error::DataEmpty )
+    }
+    if w.norm() == T::zero() || a.norm() == T::zero() {
+        return Err( error::Error::DataInvalid )
+    }
+    let mut l = 1;
+    while l < n + m - 1 {
+        l <<= 1;
+    }
+    let wf = to_f64( w );
+    let af = to_f64( a );
+    let zero = complex::Complex::new( T::zero(), T::zero() );
+
+    //y_n = x_n * a^(-n) * w^(n^2/2)
+    let mut y = vec![ zero; l ];
+    for (i, v) in x.iter().enumerate() {
+        let p = i as f64;
+        y[i] = v * complex_pow::<T>( af, -p ) * complex_pow::<T>( wf, p * p / 2. );
+    }
+    //v_j = w^(-j^2/2) for -(n-1) <= j < m, wrapped circularly
+    let mut v = vec![ zero; l ];
+    for (j, vj) in v.iter_mut().enumerate().take( m ) {
+        let p = j as f64;
+        *vj = complex_pow( wf, -p * p / 2. );
+    }
+    for j in 1..n {
+        let p = j as f64;
+        v[ l - j ] = complex_pow( wf, -p * p / 2. );
+    }
+
+    let forward = FftPlan::new( l, Direction::Forward );
+    let inverse = FftPlan::new( l, Direction::Inverse );
+    forward.process( &mut y[..] )?;
+    forward.process( &mut v[..] )?;
+    for (a, b) in y.iter_mut().zip( v.iter() ) {
+        *a *= *b;
+    }
+    inverse.process( &mut y[..] )?;
+    let scale = T::one() / T::from( l ).unwrap();
+    Ok( (0..m).map( |k| {
+        let p = k as f64;
+        y[k] * complex_pow::<T>( wf, p * p / 2. ) * scale
+    }).collect() )
+}
+
+///frequencies in Hz evaluated by zoom_fft, m points from f1 to f2 inclusive
+pub fn zoom_fft_freqs<T: FftFloat>( f1: T, f2: T, m: usize ) -> Vec<T> {
+    let step = if m > 1 { ( f2 - f1 ) / T::from( m - 1 ).unwrap() } else { T::zero() };
+    (0..m).map( |k| f1 + step * T::from( k ).unwrap() ).collect()
+}
+
+///dtft of x at m frequencies evenly spaced over [f1, f2] Hz, the unit circle arc of a czt
+pub fn zoom_fft<T: FftFloat>( x: &[complex::Complex<T>], f1: T, f2: T, m: usize, fs: T ) -> Result< Vec< complex::Complex<T> >, error::Error > {
+    if fs <= T::zero() {
+        return Err( error::Error::DataInvalid )
+    }
+    let step = if m > 1 { ( f2 - f1 ) / T::from( m - 1 ).unwrap() } else { T::zero() };
+    let two_pi = T::PI() + T::PI();
+    let a = complex::Complex::from_polar( &T::one(), &( two_pi * f1 / fs ) );
+    let w = complex::Complex::from_polar( &T::one(), &( -two_pi * step / fs ) );
+    czt( x, m, w, a )
+}
+
+#[cfg(test)]
+fn z_transform( x: &[complex::Complex<f64>], z: complex::Complex<f64> ) -> complex::Complex<f64> {
+    x.iter().enumerate().fold( complex::Complex::new( 0., 0. ), |acc, (n, v)| acc + v * z.powf( -( n as f64 ) ) )
+}
+
+#[test]
+fn test_czt_matches_fft() {
+    use fft;
+    for &n in [ 8, 13, 100 ].iter() {
+        let x = noise_complex( n, n as u64 );
+        let w = complex::Complex::from_polar( &1., &( -2. * ::std::f64::consts::PI / n as f64 ) );
+        let out = czt( &x, n, w, complex::Complex::new( 1., 0. ) ).expect("czt failed");
+        let expected = fft::fft_complex( &x );
+        out.iter().zip( expected.iter() )
+            .for_each( |v| assert!( nearly_equal( &v.0.re, &v.1.re, &1e-9 ) && nearly_equal( &v.0.im, &v.1.im, &1e-9 ) ) );
+    }
+}
+
+#[test]
+fn test_czt_spiral() {
+    //contour spiralling inward off the unit circle, more output points than input
+    let x = noise_complex( 20, 20 );
+    let a = complex::Complex::from_polar( &1.05, &0.3 );
+    let w = complex::Complex::from_polar( &1.01, &-0.05 );
+    let out = czt( &x, 37, w, a ).expect("czt failed");
+    let points = czt_points( 37, w, a );
+    assert_eq!( out.len(), 37 );
+    for (v, z) in out.iter().zip( points.iter() ) {
+        let expected = z_transform( &x, *z );
+        assert!( nearly_equal( &v.re, &expected.re, &1e-9 ) && nearly_equal( &v.im, &expected.im, &1e-9 ) );
+    }
+    assert!( czt( &x, 0, w, a ).is_err() );
+}
+
+#[test]
+fn test_zoom_fft() {
+    //line between bins of the 1 Hz fft grid, located on a 0.01 Hz grid
+    use std::f64::consts::PI;
+    let fs = 256.;
+    let x = (0..256).map( |i| {
+        let t = i as f64 / fs;
+        complex::Complex::new( ( 2. * PI * 50.1 * t ).cos(), 0. )
+    }).collect::<Vec<_>>();
+    let out = zoom_fft( &x, 49., 51., 201, fs ).expect("zoom fft failed");
+    let freqs = zoom_fft_freqs( 49., 51., 201 );
+    assert!( nearly_equal( &freqs[1], &49.01, &1e-12 ) );
+    for (v, f) in out.iter().zip( freqs.iter() ) {
+        let z = complex::Complex::from_polar( &1., &( 2. * PI * f / fs ) );
+        let expected = z_transform( &x, z );
+        assert!( nearly_equal( &v.re, &expected.re, &1e-8 ) && nearly_equal( &v.im, &expected.im, &1e-8 ) );
+    }
+    let peak = out.iter().enumerate().fold( 0, |acc, (k, v)| if v.norm() > out[acc].norm() { k } else { acc } );
+    assert!( nearly_equal( &freqs[peak], &50.1, &0.02 ) );
+}
@@ -0,0 +1,365 @@
+///Discrete cosine and sine transforms and the MDCT, computed through fft.
+///Unnormalized definitions, e.g. DCT-II is y(k) = 2 sum_n x(n) cos(pi*k*(2n+1)/(2N))
+extern crate num;
+
+use self::num::complex;
+use ndarray::Array2;
+
+use error;
+use fft;
+use fft::{ FftFloat, from_usize };
+use window::{ Symmetry, Window };
+
+#[cfg(test)]
+use test_common::{ nearly_equal, test_signal, assert_slice_eq };
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DctType {
+    I,
+    II,
+    III,
+    IV,
+}
+
+fn alternate<T: FftFloat>( x: &[T] ) -> Vec<T> {
+    x.iter().enumerate().map( |(n, v)| if n % 2 == 0 { *v } else { -*v } ).collect()
+}
+
+fn reversed<T: FftFloat>( x: &[T] ) -> Vec<T> {
+    x.iter().rev().cloned().collect()
+}
+
+///even extension of length 2N-2
+fn dct1<T: FftFloat>( x: &[T] ) -> Vec<T> {
+    let n = x.len();
+    let mut v = x.to_vec();
+    v.extend( x[ 1 .. n - 1 ].iter().rev() );
+    fft::rfft( &v[..] ).iter().take( n ).map( |c| c.re ).collect()
+}
+
+///Makhoul's reordering into a single N point transform
+fn dct2<T: FftFloat>( x: &[T] ) -> Vec<T> {
+    let n = x.len();
+    let mut v = vec![ T::zero(); n ];
+    for i in 0..( n + 1 ) / 2 {
+        v[i] = x[ 2 * i ];
+    }
+    for i in 0..n/2 {
+        v[ n - 1 - i ] = x[ 2 * i + 1 ];
+    }
+    let two = from_usize::<T>( 2 );
+    fft::fft_dif( &v[..] ).iter().enumerate().map( |(k, c)| {
+        let angle = -T::PI() * from_usize( k ) / from_usize( 2 * n );
+        two * ( c * complex::Complex::from_polar( &T::one(), &angle ) ).re
+    }).collect()
+}
+
+///exact inverse of dct2 by undoing the Makhoul reordering
+fn dct2_inverse<T: FftFloat>( y: &[T] ) -> Vec<T> {
+    let n = y.len();
+    let half = T::from( 0.5 ).unwrap();
+    let spectrum = (0..n).map( |k| {
+        let y_rev = if k == 0 { T::zero() } else { y[ n - k ] };
+        let angle = T::PI() * from_usize( k ) / from_usize( 2 * n );
+        complex::Complex::new( y[k], -y_rev ) * complex::Complex::from_polar( &half, &angle )
+    }).collect::<Vec<_>>();
+    let v = fft::ifft_complex( &spectrum[..] );
+    let mut x = vec![ T::zero(); n ];
+    for i in 0..( n + 1 ) / 2 {
+        x[ 2 * i ] = v[i].re;
+    }
+    for i in 0..n/2 {
+        x[ 2 * i + 1 ] = v[ n - 1 - i ].re;
+    }
+    x
+}
+
+fn dct3<T: FftFloat>( x: &[T] ) -> Vec<T> {
+    let scale = from_usize::<T>( 2 * x.len() );
+    dct2_inverse( x ).iter().map( |v| *v * scale ).collect()
+}
+
+///pre and post twiddled transform of length 2N
+fn dct4<T: FftFloat>( x: &[T] ) -> Vec<T> {
+    let n = x.len();
+    let mut v = vec![ complex::Complex::new( T::zero(), T::zero() ); 2 * n ];
+    for (i, xi) in x.iter().enumerate() {
+        let angle = -T::PI() * from_usize( i ) / from_usize( 2 * n );
+        v[i] = complex::Complex::from_polar( xi, &angle );
+    }
+    let two = from_usize::<T>( 2 );
+    fft::fft_complex( &v[..] ).iter().take( n ).enumerate().map( |(k, c)| {
+        let angle = -T::PI() * from_usize( 2 * k + 1 ) / from_usize( 4 * n );
+        two * ( c * complex::Complex::from_polar( &T::one(), &angle ) ).re
+    }).collect()
+}
+
+fn check_len( n: usize, kind: DctType ) -> Result< (), error::Error > {
+    match ( kind, n ) {
+        ( _, 0 ) => Err( error::Error::DataEmpty ),
+        ( DctType::I, 1 ) => Err( error::Error::DataInsufficient ),
+        _ => Ok( () ),
+    }
+}
+
+pub fn dct<T: FftFloat>( x: &[T], kind: DctType ) -> Result< Vec<T>, error::Error > {
+    check_len( x.len(), kind )?;
+    Ok( match kind {
+        DctType::I => dct1( x ),
+        DctType::II => dct2( x ),
+        DctType::III => dct3( x ),
+        DctType::IV => dct4( x ),
+    })
+}
+
+///inverse such that idct(dct(x, t), t) == x
+pub fn idct<T: FftFloat>( y: &[T], kind: DctType ) -> Result< Vec<T>, error::Error > {
+    check_len( y.len(), kind )?;
+    let n = y.len();
+    let ( x, scale ) = match kind {
+        DctType::I => ( dct1( y ), from_usize::<T>( 2 * ( n - 1 ) ) ),
+        DctType::II => ( dct2_inverse( y ), T::one() ),
+        DctType::III => ( dct2( y ), from_usize::<T>( 2 * n ) ),
+        DctType::IV => ( dct4( y ), from_usize::<T>( 2 * n ) ),
+    };
+    Ok( x.iter().map( |v| *v / scale ).collect() )
+}
+
+///odd extension of length 2N+2
+fn dst1<T: FftFloat>( x: &[T] ) -> Vec<T> {
+    let n = x.len();
+    let mut v = vec![ T::zero() ];
+    v.extend_from_slice( x );
+    v.push( T::zero() );
+    v.extend( x.iter().rev().map( |a| -*a ) );
+    fft::rfft( &v[..] ).iter().skip( 1 ).take( n ).map( |c| -c.im ).collect()
+}
+
+///DST-II: y(k) = 2 sum_n x(n) sin(pi*(k+1)*(2n+1)/(2N)), and types I, III, IV analogously
+pub fn dst<T: FftFloat>( x: &[T], kind: DctType ) -> Result< Vec<T>, error::Error > {
+    if x.is_empty() {
+        return Err( error::Error::DataEmpty )
+    }
+    Ok( match kind {
+        DctType::I => dst1( x ),
+        DctType::II => reversed( &dct2( &alternate( x )[..] )[..] ),
+        DctType::III => alternate( &dct3( &reversed( x )[..] )[..] ),
+        DctType::IV => alternate( &dct4( &reversed( x )[..] )[..] ),
+    })
+}
+
+///inverse such that idst(dst(x, t), t) == x
+pub fn idst<T: FftFloat>( y: &[T], kind: DctType ) -> Result< Vec<T>, error::Error > {
+    let n = y.len();
+    let ( x, scale ) = match kind {
+        DctType::I => ( dst( y, DctType::I )?, from_usize::<T>( 2 * ( n + 1 ) ) ),
+        DctType::II => ( dst( y, DctType::III )?, from_usize::<T>( 2 * n ) ),
+        DctType::III => ( dst( y, DctType::II )?, from_usize::<T>( 2 * n ) ),
+        DctType::IV => ( dst( y, DctType::IV )?, from_usize::<T>( 2 * n ) ),
+    };
+    Ok( x.iter().map( |v| *v / scale ).collect() )
+}
+
+///X(k) = sum_n x(n) cos(pi/N*(n+1/2+N/2)*(k+1/2)) mapping 2N samples to N coefficients, 2N divisible by 4
+pub fn mdct<T: FftFloat>( x: &[T] ) -> Result< Vec<T>, error::Error > {
+    if x.is_empty() || x.len() % 4 != 0 {
+        return Err( error::Error::Dimension )
+    }
+    let n = x.len() / 2;
+    let h = n / 2;
+    //fold quarters (a,b,c,d) into (-c_r-d, a-b_r) then a DCT-IV
+    let mut u = vec![ T::zero(); n ];
+    for i in 0..h {
+        u[i] = -x[ 3 * h - 1 - i ] - x[ 3 * h + i ];
+        u[ h + i ] = x[i] - x[ n - 1 - i ];
+    }
+    let half = T::from( 0.5 ).unwrap();
+    Ok( dct4( &u[..] ).iter().map( |v| *v * half ).collect() )
+}
+
+///y(n) = 1/N sum_k X(k) cos(pi/N*(n+1/2+N/2)*(k+1/2)), time aliased output of 2N samples
+pub fn imdct<T: FftFloat>( coeffs: &[T] ) -> Result< Vec<T>, error::Error > {
+    let n = coeffs.len();
+    if n == 0 || n % 2 != 0 {
+        return Err( error::Error::Dimension )
+    }
+    let h = n / 2;
+    let scale = T::from( 0.5 ).unwrap() / from_usize( n );
+    let u = dct4( coeffs ).iter().map( |v| *v * scale ).collect::<Vec<_>>();
+    //unfold by the odd/even symmetries of the DCT-IV basis
+    let mut y = vec![ T::zero(); 2 * n ];
+    for i in 0..h {
+        y[i] = u[ h + i ];
+        y[ 3 * h + i ] = -u[i];
+    }
+    for i in 0..n {
+        y[ h + i ] = -u[ n - 1 - i ];
+    }
+    Ok( y )
+}
+
+///sine window satisfying the Princen-Bradley condition w(n)^2 + w(n+N)^2 = 1
+pub fn mdct_sine_window<T: FftFloat>( len: usize ) -> Vec<T> {
+    (0..len).map( |i| ( T::PI() * ( from_usize::<T>( i ) + T::from( 0.5 ).unwrap() ) / from_usize( len ) ).sin() ).collect()
+}
+
+///Kaiser-Bessel-derived window of even length, satisfying the Princen-Bradley condition.
+///errors with Dimension for odd or zero lengths
+pub fn mdct_kbd_window<T: FftFloat>( len: usize, alpha: T ) -> Result< Vec<T>, error::Error > {
+    if len == 0 || len % 2 != 0 {
+        return Err( error::Error::Dimension )
+    }
+    let h = len / 2;
+    let beta = T::PI() * alpha;
+    let kaiser = Window::Kaiser( beta.to_f64().unwrap() ).coefficients::<T>( h + 1, Symmetry::Symmetric )?;
+    let total = kaiser.iter().fold( T::zero(), |acc, x| acc + *x );
+    let mut acc = T::zero();
+    let mut w = Vec::with_capacity( len );
+    for k in kaiser.iter().take( h ) {
+        acc += *k;
+        w.push( ( acc / total ).sqrt() );
+    }
+    let rev = reversed( &w[..] );
+    w.extend( rev );
+    Ok( w )
+}
+
+///windowed MDCT frames hopping by half the window length, rows are frames.
+///the signal is padded by a half window at both ends so every sample is covered by two frames
+pub fn mdct_analyze<T: FftFloat>( signal: &[T], window: &[T] ) -> Result< Array2<T>, error::Error > {
+    if window.is_empty() || window.len() % 4 != 0 {
+        return Err( error::Error::Dimension )
+    }
+    let n = window.len() / 2;
+    let frames = ( signal.len() + n - 1 ) / n + 1;
+    let mut padded = vec![ T::zero(); n ];
+    padded.extend_from_slice( signal );
+    padded.resize( ( frames + 1 ) * n, T::zero() );
+    let mut ret = Array2::from_elem( ( frames, n ), T::zero() );
+    for (i, mut row) in ret.outer_iter_mut().enumerate() {
+        let frame = padded[ i * n .. i * n + 2 * n ].iter().zip( window.iter() )
+            .map( |(x, w)| *x * *w ).collect::<Vec<_>>();
+        for (dst, src) in row.iter_mut().zip( mdct( &frame[..] )?.iter() ) {
+            *dst = *src;
+        }
+    }
+    Ok( ret )
+}
+
+///windowed IMDCT with overlap-add, time domain aliasing cancels between neighbouring frames.
+///errors when fewer than len samples are covered by two frames, as mdct_analyze pads for
+pub fn mdct_synthesize<T: FftFloat>( frames: &Array2<T>, window: &[T], len: usize ) -> Result< Vec<T>, error::Error > {
+    let ( num_frames, n ) = frames.dim();
+    if window.len() != 2 * n {
+        return Err( error::Error::Dimension )
+    }
+    if len > num_frames.saturating_sub( 1 ) * n {
+        return Err( error::Error::DataInsufficient )
+    }
+    let mut out = vec![ T::zero(); ( num_frames + 1 ) * n ];
+    for (i, row) in frames.outer_iter().enumerate() {
+        let y = imdct( &row.to_vec()[..] )?;
+        for (j, (v, w)) in y.iter().zip( window.iter() ).enumerate() {
+            out[ i * n + j ] += *v * *w;
+        }
+    }
+    //time aliasing cancels as 2*x with the 1/N scaling of imdct
+    let two = from_usize::<T>( 2 );
+    Ok( out.iter().skip( n ).take( len ).map( |v| *v * two ).collect() )
+}
+
+#[cfg(test)]
+fn naive( x: &[f64], f: &dyn Fn( usize, usize, usize ) -> f64 ) -> Vec<f64> {
+    let n = x.len();
+    (0..n).map( |k| x.iter().enumerate().map( |(i, v)| v * f( i, k, n ) ).sum() ).collect()
+}
+
+#[test]
+fn test_dct_against_definition() {
+    use std::f64::consts::PI;
+    for &n in [ 2, 3, 8, 15, 64 ].iter() {
+        let x = test_signal::<f64>( n );
+        let d1 = naive( &x, &|i, k, n| {
+            if i == 0 { 1. }
+            else if i == n - 1 { if k % 2 == 0 { 1. } else { -1. } }
+            else { 2. * ( PI * ( k * i ) as f64 / ( n - 1 ) as f64 ).cos() }
+        });
+        let d2 = naive( &x, &|i, k, n| 2. * ( PI * ( k * ( 2 * i + 1 ) ) as f64 / ( 2 * n ) as f64 ).cos() );
+        let d3 = naive( &x, &|i, k, n| if i == 0 { 1. } else { 2. * ( PI * ( i * ( 2 * k + 1 ) ) as f64 / ( 2 * n ) as f64 ).cos() } );
+        let d4 = naive( &x, &|i, k, n| 2. * ( PI * ( ( 2 * i + 1 ) * ( 2 * k + 1 ) ) as f64 / ( 4 * n ) as f64 ).cos() );
+        assert_slice_eq( &dct( &x, DctType::I ).unwrap(), &d1, 1e-9 );
+        assert_slice_eq( &dct( &x, DctType::II ).unwrap(), &d2, 1e-9 );
+        assert_slice_eq( &dct( &x, DctType::III ).unwrap(), &d3, 1e-9 );
+        assert_slice_eq( &dct( &x, DctType::IV ).unwrap(), &d4, 1e-9 );
+    }
+}
+
+#[test]
+fn test_dst_against_definition() {
+    use std::f64::consts::PI;
+    for &n in [ 1, 2, 3, 8, 15, 64 ].iter() {
+        let x = test_signal::<f64>( n );
+        let s1 = naive( &x, &|i, k, n| 2. * ( PI * ( ( k + 1 ) * ( i + 1 ) ) as f64 / ( n + 1 ) as f64 ).sin() );
+        let s2 = naive( &x, &|i, k, n| 2. * ( PI * ( ( k + 1 ) * ( 2 * i + 1 ) ) as f64 / ( 2 * n ) as f64 ).sin() );
+        let s3 = naive( &x, &|i, k, n| {
+            if i == n - 1 { if k % 2 == 0 { 1. } else { -1. } }
+            else { 2. * ( PI * ( ( 2 * k + 1 ) * ( i + 1 ) ) as f64 / ( 2 * n ) as f64 ).sin() }
+        });
+        let s4 = naive( &x, &|i, k, n| 2. * ( PI * ( ( 2 * i + 1 ) * ( 2 * k + 1 ) ) as f64 / ( 4 * n ) as f64 ).sin() );
+        assert_slice_eq( &dst( &x, DctType::I ).unwrap(), &s1, 1e-9 );
+        assert_slice_eq( &dst( &x, DctType::II ).unwrap(), &s2, 1e-9 );
+        assert_slice_eq( &dst( &x, DctType::III ).unwrap(), &s3, 1e-9 );
+        assert_slice_eq( &dst( &x, DctType::IV ).unwrap(), &s4, 1e-9 );
+    }
+}
+
+#[test]
+fn test_dct_dst_inverse() {
+    for &n in [ 2, 5, 16, 100 ].iter() {
+        let x = test_signal::<f64>( n );
+        for &kind in [ DctType::I, DctType::II, DctType::III, DctType::IV ].iter() {
+            assert_slice_eq( &idct( &dct( &x, kind ).unwrap(), kind ).unwrap(), &x, 1e-9 );
+            assert_slice_eq( &idst( &dst( &x, kind ).unwrap(), kind ).unwrap(), &x, 1e-9 );
+        }
+    }
+    assert!( dct::<f64>( &[ 1. ], DctType::I ).is_err() );
+    assert!( dct::<f32>( &[], DctType::II ).is_err() );
+}
+
+#[test]
+fn test_mdct_against_definition() {
+    use std::f64::consts::PI;
+    let n = 8;
+    let x = test_signal::<f64>( 2 * n );
+    let out = mdct( &x ).unwrap();
+    for (k, o) in out.iter().enumerate() {
+        let expected = x.iter().enumerate().map( |(i, v)| {
+            v * ( PI / n as f64 * ( i as f64 + 0.5 + n as f64 / 2. ) * ( k as f64 + 0.5 ) ).cos()
+        }).sum::<f64>();
+        assert!( nearly_equal( o, &expected, &1e-9 ) );
+    }
+    let y = imdct( &out ).unwrap();
+    for (i, yi) in y.iter().enumerate() {
+        let expected = out.iter().enumerate().map( |(k, v)| {
+            v * ( PI / n as f64 * ( i as f64 + 0.5 + n as f64 / 2. ) * ( k as f64 + 0.5 ) ).cos()
+        }).sum::<f64>() / n as f64;
+        assert!( nearly_equal( yi, &expected, &1e-9 ) );
+    }
+    assert!( mdct( &x[..6] ).is_err() );
+}
+
+#[test]
+fn test_mdct_tdac_reconstruction() {
+    let signal = test_signal::<f64>( 1000 );
+    for window in [ mdct_sine_window::<f64>( 64 ), mdct_kbd_window::<f64>( 64, 4. ).unwrap() ].iter() {
+        for i in 0..32 {
+            assert!( nearly_equal( &( window[i] * window[i] + window[i + 32] * window[i + 32] ), &1., &1e-12 ) );
+        }
+        let frames = mdct_analyze( &signal, window ).unwrap();
+        assert_eq!( frames.dim().1, 32 );
+        let out = mdct_synthesize( &frames, window, signal.len() ).unwrap();
+        assert_slice_eq( &out, &signal, 1e-9 );
+        assert!( mdct_synthesize( &frames, window, ( frames.dim().0 - 1 ) * 32 + 1 ).is_err() );
+    }
+    assert!( mdct_kbd_window::<f64>( 63, 4. ).is_err() );
+}
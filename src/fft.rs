@@ -1,157 +1,735 @@
 extern crate num;
 
 use self::num::complex;
-use std::f32;
+use self::num::traits::{ Float, FloatConst, NumAssign, Zero };
+use std::fmt::Debug;
+
+use ndarray::{ Array, Array2, ArrayD, Axis, Dimension };
+
+use error;
+use fft_simd;
+use fft_simd::Isa;
+use fft_parallel::{ ParallelFftPlan, PARALLEL_MIN_LEN };
 
 #[cfg(test)]
-use test_common::nearly_equal;
+use test_common::{ nearly_equal, test_signal };
 
 #[cfg(test)]
 const E : &f32 = &0.0001;
 
-fn pad_to_nearest_power2( arr: &[f32] ) -> Vec<f32> {
+fn pad_to_nearest_power2<T: Clone + Zero>( arr: &[T] ) -> Vec<T> {
     let n = arr.len();
     let mut i = 1;
     while i < n {
         i = i << 1;
     }
     let num_pad = i - n;
-    let mut v = vec![ 0f32; num_pad ];
+    let mut v = vec![ T::zero(); num_pad ];
     v.extend_from_slice( &arr[..] );
     v
 }
 
-fn pad_to_nearest_power2_complex( arr: &[complex::Complex<f32>] ) -> Vec<complex::Complex<f32>> {
-    let n = arr.len();
+fn pad_to_nearest_power2_back<T: Clone + Zero>( arr: &[T] ) -> Vec<T> {
     let mut i = 1;
-    while i < n {
-        i = i << 1;
+    while i < arr.len() {
+        i <<= 1;
     }
-    let num_pad = i - n;
-    let mut v = vec![ complex::Complex::new(0f32,0f32); num_pad ];
-    v.extend_from_slice( &arr[..] );
+    let mut v = arr.to_vec();
+    v.resize( i, T::zero() );
     v
 }
 
-pub fn fft_dit( arr: &[f32] ) -> Vec<complex::Complex<f32>> {
-    let v = pad_to_nearest_power2( &arr[..] );
-    let v_complex = v.iter().map(|x| complex::Complex::new( *x, 0f32 ) ).collect::<Vec<_>>();
+///floating point types the transforms are implemented for, f32 and f64
+pub trait FftFloat : Float + FloatConst + NumAssign + Debug + Send + Sync + 'static + fft_simd::sealed::Butterflies {}
+
+impl FftFloat for f32 {}
+
+impl FftFloat for f64 {}
+
+pub(crate) fn from_usize<T: FftFloat>( n: usize ) -> T {
+    T::from( n ).unwrap()
+}
+
+fn is_power2( n: usize ) -> bool {
+    n != 0 && n & (n-1) == 0
+}
+
+pub fn fft_dit<T: FftFloat>( arr: &[T] ) -> Vec<complex::Complex<T>> {
+    let v_complex = arr.iter().map(|x| complex::Complex::new( *x, T::zero() ) ).collect::<Vec<_>>();
     const EXPONENT_SIGN_POS : bool = false;
-    cooley_tukey_radix2_dit( &v_complex[..], EXPONENT_SIGN_POS )
+    if is_power2( v_complex.len() ) {
+        cooley_tukey_radix2_dit( &v_complex[..], EXPONENT_SIGN_POS )
+    } else {
+        planned( &v_complex[..], EXPONENT_SIGN_POS )
+    }
 }
 
-pub fn fft_dif( arr: &[f32] ) -> Vec<complex::Complex<f32>> {
-    let v = pad_to_nearest_power2( &arr[..] );
-    let v_complex = v.iter().map(|x| complex::Complex::new( *x, 0f32 ) ).collect::<Vec<_>>();
+pub fn fft_dif<T: FftFloat>( arr: &[T] ) -> Vec<complex::Complex<T>> {
+    let v_complex = arr.iter().map(|x| complex::Complex::new( *x, T::zero() ) ).collect::<Vec<_>>();
     const EXPONENT_SIGN_POS : bool = false;
-    cooley_tukey_radix2_dif( &v_complex[..], EXPONENT_SIGN_POS )
+    if is_power2( v_complex.len() ) {
+        cooley_tukey_radix2_dif( &v_complex[..], EXPONENT_SIGN_POS )
+    } else {
+        planned( &v_complex[..], EXPONENT_SIGN_POS )
+    }
 }
 
-pub fn ifft_dit( arr: &[complex::Complex<f32>] ) -> Vec<f32> {
-    let v_complex = pad_to_nearest_power2_complex( &arr[..] );
+pub fn ifft_dit<T: FftFloat>( arr: &[complex::Complex<T>] ) -> Vec<T> {
     const EXPONENT_SIGN_POS : bool = true;
-    let v = cooley_tukey_radix2_dit( &v_complex[..], EXPONENT_SIGN_POS );
+    let v = if is_power2( arr.len() ) {
+        cooley_tukey_radix2_dit( arr, EXPONENT_SIGN_POS )
+    } else {
+        planned( arr, EXPONENT_SIGN_POS )
+    };
     let n = v.len();
-    let ret = v.iter().map(|x| x.re / n as f32 ).collect::<Vec<f32>>();
+    let ret = v.iter().map(|x| x.re / from_usize( n ) ).collect::<Vec<T>>();
     ret
 }
 
-pub fn ifft_dif( arr: &[complex::Complex<f32>] ) -> Vec<f32> {
-    let v_complex = pad_to_nearest_power2_complex( &arr[..] );
+pub fn ifft_dif<T: FftFloat>( arr: &[complex::Complex<T>] ) -> Vec<T> {
     const EXPONENT_SIGN_POS : bool = true;
-    let v = cooley_tukey_radix2_dif( &v_complex[..], EXPONENT_SIGN_POS );
+    let v = if is_power2( arr.len() ) {
+        cooley_tukey_radix2_dif( arr, EXPONENT_SIGN_POS )
+    } else {
+        planned( arr, EXPONENT_SIGN_POS )
+    };
     let n = v.len();
-    let ret = v.iter().map(|x| x.re / n as f32 ).collect::<Vec<f32>>();
+    let ret = v.iter().map(|x| x.re / from_usize( n ) ).collect::<Vec<T>>();
     ret
 }
 
-fn cooley_tukey_radix2_dit( arr: &[complex::Complex<f32>], exponent_sign_pos: bool ) -> Vec<complex::Complex<f32>> {
-    //performs radix-2 decimation in time
-    //w = e^(sign)*2pi*i/n
-    let n = arr.len();
-    let mut ret = vec![];
-    if 1 == n {
-        ret.extend_from_slice( &arr[..] );
-        ret
+///forward transform of complex data, e.g. IQ baseband samples
+pub fn fft_complex<T: FftFloat>( arr: &[complex::Complex<T>] ) -> Vec<complex::Complex<T>> {
+    const EXPONENT_SIGN_POS : bool = false;
+    if is_power2( arr.len() ) {
+        cooley_tukey_radix2_dif( arr, EXPONENT_SIGN_POS )
     } else {
-        let i = complex::Complex::new( 0f32, 1f32 );
-        let exponent = if exponent_sign_pos {
-            // complex::Complex::new( 0f32, 2f32 * f32::consts::PI / n as f32 )
-            2f32 * f32::consts::PI * i / (n as f32)
-        } else {
-            // complex::Complex::new( 0f32, -2f32 * f32::consts::PI / n as f32 )
-            -2f32 * f32::consts::PI * i / (n as f32)
-        };
-        let w_base = exponent.exp(); //e^exponent
-        let mut w = complex::Complex::new( 1f32, 0f32 );
-        let mut y_e = vec![ complex::Complex::new( 0f32, 0f32 ); n/2 ];
-        let mut y_o = vec![ complex::Complex::new( 0f32, 0f32 ); n/2 ];
-        for j in 0..n/2 {
-            y_e[j] = arr[j*2];
-            y_o[j] = arr[j*2+1];
+        planned( arr, EXPONENT_SIGN_POS )
+    }
+}
+
+///inverse transform scaled by 1/n, keeping both real and imaginary parts
+pub fn ifft_complex<T: FftFloat>( arr: &[complex::Complex<T>] ) -> Vec<complex::Complex<T>> {
+    const EXPONENT_SIGN_POS : bool = true;
+    let v = if is_power2( arr.len() ) {
+        cooley_tukey_radix2_dif( arr, EXPONENT_SIGN_POS )
+    } else {
+        planned( arr, EXPONENT_SIGN_POS )
+    };
+    let n = from_usize::<T>( v.len() );
+    v.iter().map(|x| x / n ).collect()
+}
+
+///forward transform of real data returning the n/2+1 non-redundant bins.
+///even lengths pack pairs of samples into an n/2 point complex transform
+pub fn rfft<T: FftFloat>( arr: &[T] ) -> Vec<complex::Complex<T>> {
+    let n = arr.len();
+    if n % 2 == 1 {
+        let mut v = fft_dif( arr );
+        v.truncate( n / 2 + 1 );
+        return v
+    }
+    if n == 0 {
+        return vec![]
+    }
+    let h = n / 2;
+    let z = (0..h).map( |k| complex::Complex::new( arr[2*k], arr[2*k+1] ) ).collect::<Vec<_>>();
+    let z = planned( &z[..], false );
+    let half = T::from( 0.5 ).unwrap();
+    //split into transforms of even and odd samples: E_k = (Z_k + Z*_(h-k))/2, O_k = (Z_k - Z*_(h-k))/2i
+    (0..h+1).map( |k| {
+        let zk = z[ k % h ];
+        let zc = z[ (h - k) % h ].conj();
+        let even = ( zk + zc ) * half;
+        let odd = ( zk - zc ) * complex::Complex::new( T::zero(), -half );
+        even + twiddle::<T>( k, n, false ) * odd
+    }).collect()
+}
+
+///inverse of rfft producing n real samples from n/2+1 bins, scaled by 1/n
+pub fn irfft<T: FftFloat>( arr: &[complex::Complex<T>], n: usize ) -> Result< Vec<T>, error::Error > {
+    //mirror rfft, which maps no samples to no bins
+    if n == 0 && arr.is_empty() {
+        return Ok( vec![] )
+    }
+    if arr.len() != n / 2 + 1 {
+        return Err( error::Error::Dimension )
+    }
+    if n % 2 == 1 {
+        let mut full = arr.to_vec();
+        full.extend( arr[1..].iter().rev().map( |x| x.conj() ) );
+        return Ok( ifft_complex( &full[..] ).iter().map( |x| x.re ).collect() )
+    }
+    let h = n / 2;
+    let half = T::from( 0.5 ).unwrap();
+    //recombine even/odd sample transforms into Z_k = E_k + i*O_k
+    let z = (0..h).map( |k| {
+        let xk = arr[k];
+        let xc = arr[h - k].conj();
+        let even = ( xk + xc ) * half;
+        let odd = ( xk - xc ) * half * twiddle::<T>( k, n, true );
+        even + odd * complex::Complex::new( T::zero(), T::one() )
+    }).collect::<Vec<_>>();
+    let z = ifft_complex( &z[..] );
+    let mut ret = Vec::with_capacity( n );
+    for x in z.iter() {
+        ret.push( x.re );
+        ret.push( x.im );
+    }
+    Ok( ret )
+}
+
+///where zeros go when extending the input to a power of 2
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Padding {
+    ///zeros ahead of the signal, adds a linear phase ramp relative to Back
+    Front,
+    ///zeros after the signal
+    Back,
+    ///exact length transform without padding
+    None,
+    ///reject lengths that are not a power of 2
+    Error,
+}
+
+///scaling applied to the forward/inverse pair
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Normalization {
+    ///forward unscaled, inverse scaled by 1/n
+    Backward,
+    ///both directions scaled by 1/sqrt(n)
+    Ortho,
+    ///forward scaled by 1/n, inverse unscaled
+    Forward,
+}
+
+///bin ordering of the spectrum
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Order {
+    ///dc first, then positive then negative frequencies
+    Natural,
+    ///dc in the middle as produced by fftshift
+    Centered,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FftOptions {
+    pub padding: Padding,
+    pub normalization: Normalization,
+    pub order: Order,
+    ///worker threads for lengths from fft_parallel::PARALLEL_MIN_LEN up, 0 (the default) uses every
+    ///available core and 1 stays on the calling thread
+    pub threads: usize,
+}
+
+impl Default for FftOptions {
+    fn default() -> Self {
+        Self {
+            padding: Padding::None,
+            normalization: Normalization::Backward,
+            order: Order::Natural,
+            threads: 0,
+        }
+    }
+}
+
+///moves the zero frequency bin to the middle
+pub fn fftshift<T: Clone>( arr: &[T] ) -> Vec<T> {
+    let mut v = arr.to_vec();
+    let n = v.len();
+    v.rotate_right( n / 2 );
+    v
+}
+
+///inverse of fftshift
+pub fn ifftshift<T: Clone>( arr: &[T] ) -> Vec<T> {
+    let mut v = arr.to_vec();
+    let n = v.len();
+    v.rotate_left( n / 2 );
+    v
+}
+
+fn apply_padding<T: FftFloat>( arr: &[complex::Complex<T>], padding: Padding ) -> Result< Vec<complex::Complex<T>>, error::Error > {
+    match padding {
+        Padding::Front => Ok( pad_to_nearest_power2( arr ) ),
+        Padding::Back => Ok( pad_to_nearest_power2_back( arr ) ),
+        Padding::None => Ok( arr.to_vec() ),
+        Padding::Error => {
+            if is_power2( arr.len() ) {
+                Ok( arr.to_vec() )
+            } else {
+                Err( error::Error::Dimension )
+            }
+        },
+    }
+}
+
+fn normalization_scale<T: FftFloat>( n: usize, normalization: Normalization, exponent_sign_pos: bool ) -> T {
+    match ( normalization, exponent_sign_pos ) {
+        ( Normalization::Ortho, _ ) => T::one() / from_usize::<T>( n ).sqrt(),
+        ( Normalization::Backward, true ) | ( Normalization::Forward, false ) => T::one() / from_usize( n ),
+        _ => T::one(),
+    }
+}
+
+///forward transform with configurable padding, normalization and output order
+pub fn fft_with<T: FftFloat>( arr: &[complex::Complex<T>], options: &FftOptions ) -> Result< Vec<complex::Complex<T>>, error::Error > {
+    let v = apply_padding( arr, options.padding )?;
+    let scale = normalization_scale::<T>( v.len(), options.normalization, false );
+    let v = planned_threaded( &v[..], false, options.threads ).iter().map( |x| x * scale ).collect::<Vec<_>>();
+    match options.order {
+        Order::Natural => Ok( v ),
+        Order::Centered => Ok( fftshift( &v[..] ) ),
+    }
+}
+
+///inverse transform, with Order::Centered the input is expected in fftshift order
+pub fn ifft_with<T: FftFloat>( arr: &[complex::Complex<T>], options: &FftOptions ) -> Result< Vec<complex::Complex<T>>, error::Error > {
+    let v = match options.order {
+        Order::Natural => arr.to_vec(),
+        Order::Centered => ifftshift( arr ),
+    };
+    let v = apply_padding( &v[..], options.padding )?;
+    let scale = normalization_scale::<T>( v.len(), options.normalization, true );
+    Ok( planned_threaded( &v[..], true, options.threads ).iter().map( |x| x * scale ).collect() )
+}
+
+///transforms every 1d lane along axis in place
+fn transform_axis<T: FftFloat, D: Dimension>( arr: &mut Array<complex::Complex<T>, D>, axis: usize, exponent_sign_pos: bool ) {
+    let n = arr.len_of( Axis( axis ) );
+    let direction = if exponent_sign_pos { Direction::Inverse } else { Direction::Forward };
+    let plan = FftPlan::new( n, direction );
+    let scale = if exponent_sign_pos { T::one() / from_usize( n ) } else { T::one() };
+    let mut buf = vec![ complex::Complex::new( T::zero(), T::zero() ); n ];
+    for mut lane in arr.lanes_mut( Axis( axis ) ) {
+        for (b, x) in buf.iter_mut().zip( lane.iter() ) {
+            *b = *x;
         }
-        let y_o_ret = cooley_tukey_radix2_dit( &y_o[..], exponent_sign_pos );
-        let y_e_ret = cooley_tukey_radix2_dit( &y_e[..], exponent_sign_pos );
-        let mut y = vec![ complex::Complex::new( 0f32, 0f32 ); n ];
-        for j in 0..n/2 {
-            y[j] = y_e_ret[j] + w * y_o_ret[j];
-            y[j+n/2] = y_e_ret[j] - w * y_o_ret[j];
-            w = w * w_base;
+        plan.run( &mut buf[..] );
+        for (x, b) in lane.iter_mut().zip( buf.iter() ) {
+            *x = b * scale;
         }
-        y
     }
 }
 
-fn reverse_bits( mut input: u32, mut num_bits: u32 ) -> u32 {
-    let mut ret = 0;
-    while num_bits != 0 {
-        ret = ( ret << 1 ) | 0b1 & input;
-        num_bits -= 1;
-        input = input >> 1;
+fn transform_axes<T: FftFloat>( arr: &ArrayD<complex::Complex<T>>, axes: &[usize], exponent_sign_pos: bool ) -> Result< ArrayD<complex::Complex<T>>, error::Error > {
+    if axes.iter().any( |&axis| axis >= arr.ndim() ) {
+        return Err( error::Error::Dimension )
     }
+    let mut ret = arr.clone();
+    for &axis in axes.iter() {
+        transform_axis( &mut ret, axis, exponent_sign_pos );
+    }
+    Ok( ret )
+}
+
+///2d forward transform over rows and columns
+pub fn fft2<T: FftFloat>( arr: &Array2<complex::Complex<T>> ) -> Array2<complex::Complex<T>> {
+    let mut ret = arr.clone();
+    transform_axis( &mut ret, 0, false );
+    transform_axis( &mut ret, 1, false );
     ret
 }
 
-fn cooley_tukey_radix2_dif( arr: &[complex::Complex<f32>], exponent_sign_pos: bool ) -> Vec<complex::Complex<f32>> {
-    //iterative DIF 
-    let n = arr.len();
-    //compute tree level numbers
-    let mut levels = 0;
-    let mut n_copy = 1;
-    while n_copy < n {
-        levels += 1;
-        n_copy = n_copy << 1;
+///2d inverse transform scaled by 1/(rows*cols)
+pub fn ifft2<T: FftFloat>( arr: &Array2<complex::Complex<T>> ) -> Array2<complex::Complex<T>> {
+    let mut ret = arr.clone();
+    transform_axis( &mut ret, 0, true );
+    transform_axis( &mut ret, 1, true );
+    ret
+}
+
+///forward transform along each of the given axes
+pub fn fftn<T: FftFloat>( arr: &ArrayD<complex::Complex<T>>, axes: &[usize] ) -> Result< ArrayD<complex::Complex<T>>, error::Error > {
+    transform_axes( arr, axes, false )
+}
+
+///inverse transform along each of the given axes, scaled by 1/n per axis
+pub fn ifftn<T: FftFloat>( arr: &ArrayD<complex::Complex<T>>, axes: &[usize] ) -> Result< ArrayD<complex::Complex<T>>, error::Error > {
+    transform_axes( arr, axes, true )
+}
+
+///e^(sign*2pi*i*k/n), evaluated directly rather than by repeated multiplication
+pub(crate) fn twiddle<T: FftFloat>( k: usize, n: usize, exponent_sign_pos: bool ) -> complex::Complex<T> {
+    let angle = 2f64 * ::std::f64::consts::PI * ( k % n ) as f64 / n as f64;
+    let angle = if exponent_sign_pos { angle } else { -angle };
+    complex::Complex::new( T::from( angle.cos() ).unwrap(), T::from( angle.sin() ).unwrap() )
+}
+
+fn smallest_radix( n: usize ) -> Option<usize> {
+    [ 2, 3, 5, 7 ].iter().cloned().find( |p| n % *p == 0 )
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    ///e^(-2pi*i*j*k/n)
+    Forward,
+    ///e^(+2pi*i*j*k/n), unnormalized
+    Inverse,
+}
+
+///butterfly used for power of 2 lengths
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Kernel {
+    ///Radix4 from RADIX4_MIN_LEN up, Radix2 below
+    Auto,
+    Radix2,
+    ///radix-2^2, pairs of radix-2 levels merged into one pass with 3 twiddle multiplies per 4 outputs instead of 4
+    Radix4,
+}
+
+///below this the extra bookkeeping of radix-4 passes outweighs the saved multiplies
+const RADIX4_MIN_LEN : usize = 16;
+
+#[derive(Debug, Clone)]
+enum PlanKind<T: FftFloat> {
+    Identity,
+    ///iterative radix-2 over bit reversed input, levels[s][j] = w_m^j for group size m = 2^(s+1)
+    Radix2 {
+        rev: Vec<usize>,
+        levels: Vec<Vec<complex::Complex<T>>>,
+    },
+    ///iterative radix-4 over the same bit reversed order, levels[s] = [w_m^j, w_m^2j, w_m^3j] for j < m/4,
+    ///after a leading radix-2 pass when the number of levels is odd
+    Radix4 {
+        rev: Vec<usize>,
+        levels: Vec<[ Vec<complex::Complex<T>>; 3 ]>,
+    },
+    ///one decimation in time step of the given radix over a sub-plan of length n/radix
+    MixedRadix {
+        radix: usize,
+        twiddles: Vec<complex::Complex<T>>,
+        twiddles_radix: Vec<complex::Complex<T>>,
+        sub: Box<FftPlan<T>>,
+    },
+    ///chirp-z convolution through power of 2 sub-plans
+    Bluestein {
+        chirp: Vec<complex::Complex<T>>,
+        kernel_freq: Vec<complex::Complex<T>>,
+        forward: Box<FftPlan<T>>,
+        inverse: Box<FftPlan<T>>,
+    },
+}
+
+///precomputed twiddles and permutation tables for repeated transforms of one size and direction
+#[derive(Debug, Clone)]
+pub struct FftPlan<T: FftFloat> {
+    n: usize,
+    direction: Direction,
+    isa: Isa,
+    kind: PlanKind<T>,
+}
+
+impl<T: FftFloat> FftPlan<T> {
+
+    pub fn new( n: usize, direction: Direction ) -> FftPlan<T> {
+        FftPlan::with_kernel( n, direction, Kernel::Auto )
     }
 
-    //re-arrange bottom level of the tree
-    let mut output = vec![ complex::Complex::new( 0f32, 0f32 ); n ];
-    for i in 0..n {
-        let rev_num = reverse_bits(i as u32, levels);
-        output[rev_num as usize] = arr[i];
-    }
-    for s in 0..levels { //for each tree level
-        let m = 1 << (s+1); //2^(s+1) = decimation group size
-        let i = complex::Complex::new( 0f32, 1f32 );
-        let exponent = if exponent_sign_pos {
-            2f32 * f32::consts::PI * i / (m as f32)
+    ///kernel selects the butterflies for power of 2 lengths, other lengths are unaffected
+    pub fn with_kernel( n: usize, direction: Direction, kernel: Kernel ) -> FftPlan<T> {
+        let exponent_sign_pos = direction == Direction::Inverse;
+        let kind = if n <= 1 {
+            PlanKind::Identity
+        } else if is_power2( n ) {
+            let levels = n.trailing_zeros();
+            let rev = (0..n).map( |i| reverse_bits( i as u32, levels ) as usize ).collect();
+            let use_radix4 = match kernel {
+                Kernel::Auto => n >= RADIX4_MIN_LEN,
+                Kernel::Radix2 => false,
+                Kernel::Radix4 => true,
+            };
+            //per level tables keep the twiddles of a pass contiguous for vector loads
+            if use_radix4 {
+                let mut levels = vec![];
+                let mut m = if levels_odd( n ) { 8 } else { 4 };
+                while m <= n {
+                    let table = |p: usize| (0..m/4).map( |j| twiddle( p * j, m, exponent_sign_pos ) ).collect();
+                    levels.push( [ table( 1 ), table( 2 ), table( 3 ) ] );
+                    m <<= 2;
+                }
+                PlanKind::Radix4 { rev, levels }
+            } else {
+                let levels = (1..=n.trailing_zeros()).map( |s| {
+                    let m = 1 << s;
+                    (0..m/2).map( |j| twiddle( j, m, exponent_sign_pos ) ).collect()
+                } ).collect();
+                PlanKind::Radix2 { rev, levels }
+            }
         } else {
-            -2f32 * f32::consts::PI * i / (m as f32)
+            match smallest_radix( n ) {
+                Some(radix) => {
+                    PlanKind::MixedRadix {
+                        radix,
+                        twiddles: (0..n).map( |k| twiddle( k, n, exponent_sign_pos ) ).collect(),
+                        twiddles_radix: (0..radix).map( |k| twiddle( k, radix, exponent_sign_pos ) ).collect(),
+                        sub: Box::new( FftPlan::new( n / radix, direction ) ),
+                    }
+                },
+                _ => {
+                    FftPlan::new_bluestein( n, direction )
+                },
+            }
         };
-        let w_base = exponent.exp(); //e^exponent = primitive m'th root of unity
-        let mut k = 0;
-        while k < n { //for each butterfly group
-            let mut w = complex::Complex::new( 1f32, 0f32 ); //twiddle factor
-            for j in 0..m/2 { //for each butterfly pair in current group
-                let odd = w * output[ k + j + m/2 ];
-                let even = output[ k + j ];
-                output[ k + j ] = even + odd;
-                output[ k + j + m/2 ] = even - odd;
-                w = w * w_base;
+        FftPlan {
+            n,
+            direction,
+            isa: fft_simd::detect(),
+            kind,
+        }
+    }
+
+    fn new_bluestein( n: usize, direction: Direction ) -> PlanKind<T> {
+        //using j*k = ( j^2 + k^2 - (k-j)^2 ) / 2
+        let exponent_sign_pos = direction == Direction::Inverse;
+        let mut m = 1;
+        while m < 2 * n - 1 {
+            m <<= 1;
+        }
+        //chirp c_k = e^(sign*pi*i*k^2/n), k^2 reduced mod 2n to keep the angle small
+        let chirp = (0..n).map( |k| twiddle( ( k * k ) % ( 2 * n ), 2 * n, exponent_sign_pos ) )
+            .collect::<Vec<_>>();
+        let mut kernel = vec![ complex::Complex::new( T::zero(), T::zero() ); m ];
+        kernel[0] = chirp[0].conj();
+        for k in 1..n {
+            kernel[k] = chirp[k].conj();
+            kernel[m-k] = chirp[k].conj();
+        }
+        let forward = FftPlan::new( m, Direction::Forward );
+        forward.run( &mut kernel[..] );
+        PlanKind::Bluestein {
+            chirp,
+            kernel_freq: kernel,
+            forward: Box::new( forward ),
+            inverse: Box::new( FftPlan::new( m, Direction::Inverse ) ),
+        }
+    }
+
+    pub fn len( &self ) -> usize {
+        self.n
+    }
+
+    pub fn is_empty( &self ) -> bool {
+        self.n == 0
+    }
+
+    pub fn direction( &self ) -> Direction {
+        self.direction
+    }
+
+    ///instruction set of the power of 2 butterflies, detected at construction
+    pub fn isa( &self ) -> Isa {
+        self.isa
+    }
+
+    ///overrides the detected instruction set here and in all sub-plans, errors if the cpu lacks it
+    pub fn set_isa( &mut self, isa: Isa ) -> Result< (), error::Error > {
+        if !fft_simd::is_supported( isa ) {
+            return Err( error::Error::Operation )
+        }
+        self.isa = isa;
+        match self.kind {
+            PlanKind::MixedRadix { ref mut sub, .. } => sub.set_isa( isa ),
+            PlanKind::Bluestein { ref mut forward, ref mut inverse, .. } => {
+                forward.set_isa( isa )?;
+                inverse.set_isa( isa )
+            },
+            _ => Ok( () ),
+        }
+    }
+
+    ///butterfly in use for power of 2 lengths, Auto for other lengths
+    pub fn kernel( &self ) -> Kernel {
+        match self.kind {
+            PlanKind::Radix2 { .. } => Kernel::Radix2,
+            PlanKind::Radix4 { .. } => Kernel::Radix4,
+            _ => Kernel::Auto,
+        }
+    }
+
+    ///transforms buf in place, buf length must equal the planned length
+    pub fn process( &self, buf: &mut [complex::Complex<T>] ) -> Result< (), error::Error > {
+        if buf.len() != self.n {
+            Err( error::Error::Dimension )
+        } else {
+            self.run( buf );
+            Ok( () )
+        }
+    }
+
+    pub(crate) fn run( &self, buf: &mut [complex::Complex<T>] ) {
+        match self.kind {
+            PlanKind::Identity => {},
+            PlanKind::Radix2 { ref rev, ref levels } => {
+                radix2_in_place( buf, rev, levels, self.isa );
+            },
+            PlanKind::Radix4 { ref rev, ref levels } => {
+                radix4_in_place( buf, rev, levels, self.direction == Direction::Inverse, self.isa );
+            },
+            PlanKind::MixedRadix { radix, ref twiddles, ref twiddles_radix, ref sub } => {
+                mixed_radix_in_place( buf, radix, twiddles, twiddles_radix, sub );
+            },
+            PlanKind::Bluestein { ref chirp, ref kernel_freq, ref forward, ref inverse } => {
+                let m = kernel_freq.len();
+                let mut a = vec![ complex::Complex::new( T::zero(), T::zero() ); m ];
+                for k in 0..self.n {
+                    a[k] = buf[k] * chirp[k];
+                }
+                forward.run( &mut a[..] );
+                for (x, y) in a.iter_mut().zip( kernel_freq.iter() ) {
+                    *x *= *y;
+                }
+                inverse.run( &mut a[..] );
+                for k in 0..self.n {
+                    buf[k] = chirp[k] * a[k] / from_usize::<T>( m );
+                }
+            },
+        }
+    }
+}
+
+fn levels_odd( n: usize ) -> bool {
+    n.trailing_zeros() % 2 == 1
+}
+
+fn bit_reverse_permute<T: Copy>( buf: &mut [T], rev: &[usize] ) {
+    for (i, &j) in rev.iter().enumerate() {
+        if i < j {
+            buf.swap( i, j );
+        }
+    }
+}
+
+fn radix2_in_place<T: FftFloat>( buf: &mut [complex::Complex<T>], rev: &[usize], levels: &[Vec<complex::Complex<T>>], isa: Isa ) {
+    //re-arrange bottom level of the tree
+    bit_reverse_permute( buf, rev );
+    for twiddles in levels {
+        //for each butterfly group of size 2 * |twiddles|
+        for group in buf.chunks_mut( 2 * twiddles.len() ) {
+            T::butterfly2( isa, group, twiddles );
+        }
+    }
+}
+
+fn radix4_in_place<T: FftFloat>( buf: &mut [complex::Complex<T>], rev: &[usize], levels: &[[ Vec<complex::Complex<T>>; 3 ]], exponent_sign_pos: bool, isa: Isa ) {
+    let n = buf.len();
+    //re-arrange bottom level of the tree
+    bit_reverse_permute( buf, rev );
+    //an odd number of levels leaves one radix-2 pass at the bottom
+    if levels_odd( n ) {
+        for pair in buf.chunks_mut( 2 ) {
+            let ( even, odd ) = ( pair[0], pair[1] );
+            pair[0] = even + odd;
+            pair[1] = even - odd;
+        }
+    }
+    for tw in levels {
+        //quarters hold transforms of residues 0, 2, 1, 3 mod 4 in bit reversed order
+        for group in buf.chunks_mut( 4 * tw[0].len() ) {
+            T::butterfly4( isa, group, [ &tw[0][..], &tw[1][..], &tw[2][..] ], exponent_sign_pos );
+        }
+    }
+}
+
+fn mixed_radix_in_place<T: FftFloat>( buf: &mut [complex::Complex<T>],
+                         radix: usize,
+                         twiddles: &[complex::Complex<T>],
+                         twiddles_radix: &[complex::Complex<T>],
+                         sub: &FftPlan<T> ) {
+    let n = buf.len();
+    let m = n / radix;
+
+    //sub-transforms of x[radix*j+r] for each residue r, stored contiguously
+    let mut subs = vec![ complex::Complex::new( T::zero(), T::zero() ); n ];
+    for r in 0..radix {
+        for j in 0..m {
+            subs[ r * m + j ] = buf[ radix * j + r ];
+        }
+        sub.run( &mut subs[ r * m .. (r+1) * m ] );
+    }
+
+    //X[k+q*m] = sum_r ( w_n^(r*k) * Y_r[k] ) * w_radix^(r*q)
+    let mut t = vec![ complex::Complex::new( T::zero(), T::zero() ); radix ];
+    for k in 0..m {
+        for r in 0..radix {
+            t[r] = twiddles[ r * k ] * subs[ r * m + k ];
+        }
+        for q in 0..radix {
+            let mut acc = complex::Complex::new( T::zero(), T::zero() );
+            for r in 0..radix {
+                acc += t[r] * twiddles_radix[ (r * q) % radix ];
             }
-            k += m;
+            buf[ k + q * m ] = acc;
         }
     }
-    output
+}
+
+///lengths from PARALLEL_MIN_LEN up are split over every available core
+fn planned<T: FftFloat>( arr: &[complex::Complex<T>], exponent_sign_pos: bool ) -> Vec<complex::Complex<T>> {
+    planned_threaded( arr, exponent_sign_pos, 0 )
+}
+
+///lengths from PARALLEL_MIN_LEN up are split over threads workers, 0 uses every available core
+///and 1 keeps the transform on the calling thread
+fn planned_threaded<T: FftFloat>( arr: &[complex::Complex<T>], exponent_sign_pos: bool, threads: usize ) -> Vec<complex::Complex<T>> {
+    let direction = if exponent_sign_pos { Direction::Inverse } else { Direction::Forward };
+    let mut v = arr.to_vec();
+    if v.len() < PARALLEL_MIN_LEN || threads == 1 {
+        FftPlan::new( v.len(), direction ).run( &mut v[..] );
+    } else {
+        ParallelFftPlan::new( v.len(), direction, threads ).run( &mut v[..] );
+    }
+    v
+}
+
+fn cooley_tukey_radix2_dit<T: FftFloat>( arr: &[complex::Complex<T>], exponent_sign_pos: bool ) -> Vec<complex::Complex<T>> {
+    //performs radix-2 decimation in time
+    //w = e^(sign)*2pi*i/n
+    let n = arr.len();
+    if n >= PARALLEL_MIN_LEN {
+        return planned( arr, exponent_sign_pos )
+    }
+    let twiddles = (0..n/2).map( |k| twiddle( k, n, exponent_sign_pos ) ).collect::<Vec<_>>();
+    let mut y = vec![ complex::Complex::new( T::zero(), T::zero() ); n ];
+    dit_recursive( arr, 1, &mut y[..], &twiddles[..], 1 );
+    y
+}
+
+///transforms the strided input into out, sub-transforms of even and odd samples
+///land in the two halves of out so no temporaries are allocated
+fn dit_recursive<T: FftFloat>( arr: &[complex::Complex<T>], stride: usize, out: &mut [complex::Complex<T>], twiddles: &[complex::Complex<T>], twiddle_stride: usize ) {
+    let n = out.len();
+    if n == 1 {
+        out[0] = arr[0];
+        return
+    }
+    {
+        let ( y_e, y_o ) = out.split_at_mut( n/2 );
+        dit_recursive( arr, stride * 2, y_e, twiddles, twiddle_stride * 2 );
+        dit_recursive( &arr[ stride.. ], stride * 2, y_o, twiddles, twiddle_stride * 2 );
+    }
+    for j in 0..n/2 {
+        let w = twiddles[ j * twiddle_stride ];
+        let even = out[j];
+        let odd = w * out[ j + n/2 ];
+        out[j] = even + odd;
+        out[ j + n/2 ] = even - odd;
+    }
+}
+
+pub(crate) fn reverse_bits( mut input: u32, mut num_bits: u32 ) -> u32 {
+    let mut ret = 0;
+    while num_bits != 0 {
+        ret = ( ret << 1 ) | 0b1 & input;
+        num_bits -= 1;
+        input = input >> 1;
+    }
+    ret
+}
+
+fn cooley_tukey_radix2_dif<T: FftFloat>( arr: &[complex::Complex<T>], exponent_sign_pos: bool ) -> Vec<complex::Complex<T>> {
+    //iterative DIF over precomputed bit reversal and twiddle tables
+    planned( arr, exponent_sign_pos )
 }
 
 
@@ -170,6 +748,15 @@ fn test_padding() {
     assert_eq!( out3.len(), 1 );
 }
 
+#[test]
+fn test_padding_back() {
+    let arr = vec![ 1f32, 2., 3. ];
+    let front = pad_to_nearest_power2( &arr[..] );
+    let back = pad_to_nearest_power2_back( &arr[..] );
+    assert_eq!( front, vec![ 0., 1., 2., 3. ] );
+    assert_eq!( back, vec![ 1., 2., 3., 0. ] );
+}
+
 #[test]
 fn test_fft_dit() {
     let arr = vec![ 0., 2., 2., 0. ];
@@ -297,3 +884,363 @@ fn test_fft_dif_3() {
         .for_each( |x| assert!( nearly_equal( &x.0.re, &x.1.re, E ) &&
                                 nearly_equal( &x.0.im, &x.1.im, E ) ) );
 }
+
+#[cfg(test)]
+fn dft_naive( arr: &[complex::Complex<f32>], exponent_sign_pos: bool ) -> Vec<complex::Complex<f32>> {
+    let n = arr.len();
+    (0..n).map( |k| {
+        arr.iter().enumerate().fold( complex::Complex::new( 0f32, 0f32 ), |acc, (j,x)| {
+            acc + x * twiddle( j * k, n, exponent_sign_pos )
+        })
+    }).collect()
+}
+
+#[test]
+fn test_fft_arbitrary_length() {
+    for &n in [ 3, 6, 12, 15, 49, 11, 13, 22, 105, 1000 ].iter() {
+        let arr = test_signal::<f32>( n );
+        let arr_complex = arr.iter().map( |x| complex::Complex::new( *x, 0f32 ) ).collect::<Vec<_>>();
+        let expected = dft_naive( &arr_complex[..], false );
+        let tol = 0.0005 * n as f32;
+        for out in [ fft_dit( &arr[..] ), fft_dif( &arr[..] ) ].iter() {
+            assert_eq!( out.len(), n );
+            expected.iter().zip( out.iter() )
+                .for_each( |x| assert!( nearly_equal( &x.0.re, &x.1.re, &tol ) &&
+                                        nearly_equal( &x.0.im, &x.1.im, &tol ) ) );
+        }
+    }
+}
+
+#[test]
+fn test_bluestein_prime() {
+    let arr = test_signal::<f32>( 17 ).iter().map( |x| complex::Complex::new( *x, -x ) ).collect::<Vec<_>>();
+    let plan = FftPlan::new( 17, Direction::Forward );
+    let mut out = arr.clone();
+    plan.process( &mut out[..] ).expect("plan length mismatch");
+    let expected = dft_naive( &arr[..], false );
+    assert_eq!( out.len(), 17 );
+    expected.iter().zip( out.iter() )
+        .for_each( |x| assert!( nearly_equal( &x.0.re, &x.1.re, E ) &&
+                                nearly_equal( &x.0.im, &x.1.im, E ) ) );
+}
+
+#[test]
+fn test_ifft_arbitrary_length() {
+    for &n in [ 6, 7, 30, 1000 ].iter() {
+        let arr = test_signal::<f32>( n );
+        let out_dit = ifft_dit( &fft_dit( &arr[..] )[..] );
+        let out_dif = ifft_dif( &fft_dif( &arr[..] )[..] );
+        assert_eq!( out_dit.len(), n );
+        assert_eq!( out_dif.len(), n );
+        arr.iter().zip( out_dit.iter().zip( out_dif.iter() ) )
+            .for_each( |x| assert!( nearly_equal( x.0, (x.1).0, E ) &&
+                                    nearly_equal( x.0, (x.1).1, E ) ) );
+    }
+}
+
+#[test]
+fn test_plan_reuse() {
+    for &n in [ 1, 2, 16, 1024, 12, 210, 97 ].iter() {
+        let fwd = FftPlan::new( n, Direction::Forward );
+        let inv = FftPlan::new( n, Direction::Inverse );
+        assert_eq!( fwd.len(), n );
+        let tol = 0.0005 * n as f32;
+        for shift in 0..3 {
+            let arr = test_signal::<f32>( n + shift )[shift..].iter()
+                .map( |x| complex::Complex::new( *x, 0.5 * x ) ).collect::<Vec<_>>();
+            let expected = dft_naive( &arr[..], false );
+            let mut buf = arr.clone();
+            fwd.process( &mut buf[..] ).expect("plan length mismatch");
+            expected.iter().zip( buf.iter() )
+                .for_each( |x| assert!( nearly_equal( &x.0.re, &x.1.re, &tol ) &&
+                                        nearly_equal( &x.0.im, &x.1.im, &tol ) ) );
+            inv.process( &mut buf[..] ).expect("plan length mismatch");
+            arr.iter().zip( buf.iter() )
+                .for_each( |x| assert!( nearly_equal( &x.0.re, &(x.1.re / n as f32), E ) &&
+                                        nearly_equal( &x.0.im, &(x.1.im / n as f32), E ) ) );
+        }
+    }
+}
+
+#[test]
+fn test_plan_length_mismatch() {
+    let plan = FftPlan::new( 8, Direction::Forward );
+    let mut buf = vec![ complex::Complex::new( 0f32, 0f32 ); 7 ];
+    assert!( plan.process( &mut buf[..] ).is_err() );
+}
+
+#[test]
+fn test_fft_f64_precision() {
+    //single tone over a long dwell, all energy should land in two bins
+    for &n in [ 65536usize, 100000 ].iter() {
+        let f0 = 1234;
+        let arr = (0..n).map( |i| ( 2. * ::std::f64::consts::PI * ( f0 * i ) as f64 / n as f64 ).cos() )
+            .collect::<Vec<f64>>();
+        let out = fft_dif( &arr[..] );
+        assert_eq!( out.len(), n );
+        for (k, x) in out.iter().enumerate() {
+            let expected = if k == f0 || k == n - f0 { n as f64 / 2. } else { 0. };
+            assert!( nearly_equal( &x.re, &expected, &1e-7 ) && nearly_equal( &x.im, &0., &1e-7 ) );
+        }
+        let back = ifft_dif( &out[..] );
+        arr.iter().zip( back.iter() )
+            .for_each( |x| assert!( nearly_equal( x.0, x.1, &1e-12 ) ) );
+    }
+}
+
+#[test]
+fn test_fft_complex() {
+    for &n in [ 8, 64, 24, 19 ].iter() {
+        let arr = test_signal::<f32>( n ).iter().enumerate()
+            .map( |(i,x)| complex::Complex::new( *x, ( i as f32 * 0.11 ).cos() ) ).collect::<Vec<_>>();
+        let out = fft_complex( &arr[..] );
+        let expected = dft_naive( &arr[..], false );
+        assert_eq!( out.len(), n );
+        expected.iter().zip( out.iter() )
+            .for_each( |x| assert!( nearly_equal( &x.0.re, &x.1.re, &0.001 ) &&
+                                    nearly_equal( &x.0.im, &x.1.im, &0.001 ) ) );
+        let back = ifft_complex( &out[..] );
+        assert_eq!( back.len(), n );
+        arr.iter().zip( back.iter() )
+            .for_each( |x| assert!( nearly_equal( &x.0.re, &x.1.re, E ) &&
+                                    nearly_equal( &x.0.im, &x.1.im, E ) ) );
+    }
+}
+
+#[test]
+fn test_ifft_complex_keeps_imaginary() {
+    //a single positive frequency bin is a complex exponential in time
+    let mut arr = vec![ complex::Complex::new( 0f64, 0f64 ); 8 ];
+    arr[1] = complex::Complex::new( 8., 0. );
+    let out = ifft_complex( &arr[..] );
+    for (j, x) in out.iter().enumerate() {
+        let angle = 2. * ::std::f64::consts::PI * j as f64 / 8.;
+        assert!( nearly_equal( &x.re, &angle.cos(), &1e-12 ) &&
+                 nearly_equal( &x.im, &angle.sin(), &1e-12 ) );
+    }
+}
+
+#[test]
+fn test_rfft() {
+    for &n in [ 2, 8, 64, 20, 1000, 7, 15 ].iter() {
+        let arr = test_signal::<f32>( n );
+        let out = rfft( &arr[..] );
+        let expected = fft_dif( &arr[..] );
+        let tol = 0.0005 * n as f32;
+        assert_eq!( out.len(), n / 2 + 1 );
+        expected.iter().zip( out.iter() )
+            .for_each( |x| assert!( nearly_equal( &x.0.re, &x.1.re, &tol ) &&
+                                    nearly_equal( &x.0.im, &x.1.im, &tol ) ) );
+    }
+}
+
+#[test]
+fn test_irfft() {
+    for &n in [ 2, 8, 64, 20, 1000, 7, 15 ].iter() {
+        let arr = test_signal::<f32>( n ).iter().map( |x| *x as f64 ).collect::<Vec<_>>();
+        let out = irfft( &rfft( &arr[..] )[..], n ).expect("irfft failed");
+        assert_eq!( out.len(), n );
+        arr.iter().zip( out.iter() )
+            .for_each( |x| assert!( nearly_equal( x.0, x.1, &1e-10 ) ) );
+    }
+    let spectrum = vec![ complex::Complex::new( 1f32, 0f32 ); 4 ];
+    assert!( irfft( &spectrum[..], 8 ).is_err() );
+    let empty: Vec<f64> = vec![];
+    assert!( irfft( &rfft( &empty[..] )[..], 0 ).expect("irfft failed").is_empty() );
+}
+
+#[test]
+fn test_fftshift() {
+    assert_eq!( fftshift( &[ 0, 1, 2, 3, 4 ] ), vec![ 3, 4, 0, 1, 2 ] );
+    assert_eq!( ifftshift( &[ 3, 4, 0, 1, 2 ] ), vec![ 0, 1, 2, 3, 4 ] );
+    assert_eq!( fftshift( &[ 0, 1, 2, 3 ] ), vec![ 2, 3, 0, 1 ] );
+    assert_eq!( ifftshift( &[ 2, 3, 0, 1 ] ), vec![ 0, 1, 2, 3 ] );
+}
+
+#[test]
+fn test_fft_with_options() {
+    let arr = test_signal::<f32>( 6 ).iter().map( |x| complex::Complex::new( *x as f64, 0. ) ).collect::<Vec<_>>();
+
+    let strict = FftOptions { padding: Padding::Error, ..FftOptions::default() };
+    assert!( fft_with( &arr[..], &strict ).is_err() );
+
+    //back padding matches an explicitly zero extended transform
+    let back = FftOptions { padding: Padding::Back, ..FftOptions::default() };
+    let out = fft_with( &arr[..], &back ).expect("fft failed");
+    let mut extended = arr.clone();
+    extended.resize( 8, complex::Complex::new( 0., 0. ) );
+    let expected = fft_complex( &extended[..] );
+    assert_eq!( out.len(), 8 );
+    expected.iter().zip( out.iter() )
+        .for_each( |x| assert!( nearly_equal( &x.0.re, &x.1.re, &1e-12 ) &&
+                                nearly_equal( &x.0.im, &x.1.im, &1e-12 ) ) );
+
+    //front padding differs from back padding by a linear phase ramp of 2 samples
+    let front = FftOptions { padding: Padding::Front, ..FftOptions::default() };
+    let out_front = fft_with( &arr[..], &front ).expect("fft failed");
+    for k in 0..8 {
+        let ramp = out[k] * twiddle::<f64>( 2 * k, 8, false );
+        assert!( nearly_equal( &ramp.re, &out_front[k].re, &1e-12 ) &&
+                 nearly_equal( &ramp.im, &out_front[k].im, &1e-12 ) );
+    }
+
+    //every normalization round trips
+    for &normalization in [ Normalization::Backward, Normalization::Ortho, Normalization::Forward ].iter() {
+        for &order in [ Order::Natural, Order::Centered ].iter() {
+            let options = FftOptions { normalization, order, ..FftOptions::default() };
+            let spectrum = fft_with( &arr[..], &options ).expect("fft failed");
+            let back = ifft_with( &spectrum[..], &options ).expect("ifft failed");
+            arr.iter().zip( back.iter() )
+                .for_each( |x| assert!( nearly_equal( &x.0.re, &x.1.re, &1e-12 ) &&
+                                        nearly_equal( &x.0.im, &x.1.im, &1e-12 ) ) );
+        }
+    }
+
+    //ortho preserves energy
+    let options = FftOptions { normalization: Normalization::Ortho, ..FftOptions::default() };
+    let spectrum = fft_with( &arr[..], &options ).expect("fft failed");
+    let energy_t = arr.iter().map( |x| x.norm_sqr() ).sum::<f64>();
+    let energy_f = spectrum.iter().map( |x| x.norm_sqr() ).sum::<f64>();
+    assert!( nearly_equal( &energy_t, &energy_f, &1e-12 ) );
+}
+
+#[test]
+fn test_fft2() {
+    let ( rows, cols ) = ( 6, 8 );
+    let arr = Array2::from_shape_fn( ( rows, cols ), |(i,j)| {
+        complex::Complex::new( ( ( i * cols + j ) as f64 * 0.3 ).sin(), ( j as f64 * 0.7 ).cos() )
+    });
+    let out = fft2( &arr );
+    //direct 2d dft
+    for k0 in 0..rows {
+        for k1 in 0..cols {
+            let mut acc = complex::Complex::new( 0., 0. );
+            for i in 0..rows {
+                for j in 0..cols {
+                    acc += arr[[i,j]] * twiddle::<f64>( i * k0, rows, false ) * twiddle::<f64>( j * k1, cols, false );
+                }
+            }
+            assert!( nearly_equal( &acc.re, &out[[k0,k1]].re, &1e-10 ) &&
+                     nearly_equal( &acc.im, &out[[k0,k1]].im, &1e-10 ) );
+        }
+    }
+    let back = ifft2( &out );
+    arr.iter().zip( back.iter() )
+        .for_each( |x| assert!( nearly_equal( &x.0.re, &x.1.re, &1e-12 ) &&
+                                nearly_equal( &x.0.im, &x.1.im, &1e-12 ) ) );
+}
+
+#[test]
+fn test_fftn_axes() {
+    let arr = ArrayD::from_shape_fn( vec![ 4, 3, 5 ], |idx| {
+        complex::Complex::new( ( idx[0] + 2 * idx[1] ) as f64, ( idx[2] as f64 * 0.5 ).sin() )
+    });
+    let out = fftn( &arr, &[ 2 ] ).expect("fftn failed");
+    for i in 0..4 {
+        for j in 0..3 {
+            let lane = arr.slice( s![ i, j, .. ] ).to_vec();
+            let expected = fft_complex( &lane[..] );
+            for k in 0..5 {
+                assert!( nearly_equal( &expected[k].re, &out[[i,j,k]].re, &1e-12 ) &&
+                         nearly_equal( &expected[k].im, &out[[i,j,k]].im, &1e-12 ) );
+            }
+        }
+    }
+    let all = fftn( &arr, &[ 0, 1, 2 ] ).expect("fftn failed");
+    let back = ifftn( &all, &[ 2, 1, 0 ] ).expect("ifftn failed");
+    arr.iter().zip( back.iter() )
+        .for_each( |x| assert!( nearly_equal( &x.0.re, &x.1.re, &1e-12 ) &&
+                                nearly_equal( &x.0.im, &x.1.im, &1e-12 ) ) );
+    assert!( fftn( &arr, &[ 3 ] ).is_err() );
+}
+
+#[test]
+fn test_radix4_kernel() {
+    for levels in 0..13 {
+        let n = 1 << levels;
+        for &direction in [ Direction::Forward, Direction::Inverse ].iter() {
+            let radix2 = FftPlan::<f64>::with_kernel( n, direction, Kernel::Radix2 );
+            let radix4 = FftPlan::<f64>::with_kernel( n, direction, Kernel::Radix4 );
+            let arr = test_signal::<f32>( n ).iter().enumerate()
+                .map( |(i, x)| complex::Complex::new( *x as f64, ( i as f64 * 0.3 ).cos() ) ).collect::<Vec<_>>();
+            let mut out2 = arr.clone();
+            let mut out4 = arr.clone();
+            radix2.process( &mut out2[..] ).expect("plan length mismatch");
+            radix4.process( &mut out4[..] ).expect("plan length mismatch");
+            out2.iter().zip( out4.iter() )
+                .for_each( |x| assert!( nearly_equal( &x.0.re, &x.1.re, &1e-9 ) &&
+                                        nearly_equal( &x.0.im, &x.1.im, &1e-9 ) ) );
+        }
+    }
+    assert_eq!( FftPlan::<f32>::new( 1024, Direction::Forward ).kernel(), Kernel::Radix4 );
+    assert_eq!( FftPlan::<f32>::new( 8, Direction::Forward ).kernel(), Kernel::Radix2 );
+    assert_eq!( FftPlan::<f32>::new( 12, Direction::Forward ).kernel(), Kernel::Auto );
+}
+
+#[test]
+fn test_dit_recursive_matches_dif() {
+    for levels in 0..11 {
+        let n = 1 << levels;
+        let arr = test_signal::<f32>( n ).iter().map( |x| complex::Complex::new( *x as f64, -x as f64 ) ).collect::<Vec<_>>();
+        for &sign in [ false, true ].iter() {
+            let dit = cooley_tukey_radix2_dit( &arr[..], sign );
+            let dif = cooley_tukey_radix2_dif( &arr[..], sign );
+            dit.iter().zip( dif.iter() )
+                .for_each( |x| assert!( nearly_equal( &x.0.re, &x.1.re, &1e-9 ) &&
+                                        nearly_equal( &x.0.im, &x.1.im, &1e-9 ) ) );
+        }
+    }
+}
+
+#[test]
+fn test_simd_plans_match_scalar() {
+    //power of 2 sizes with both level parities, plus mixed radix and bluestein sub-plans
+    for &n in [ 16, 32, 1024, 2048, 96, 97 ].iter() {
+        for &kernel in [ Kernel::Radix2, Kernel::Radix4 ].iter() {
+            for &direction in [ Direction::Forward, Direction::Inverse ].iter() {
+                let arr = test_signal::<f32>( n ).iter().enumerate()
+                    .map( |(i, x)| complex::Complex::new( *x, ( i as f32 * 0.7 ).sin() ) ).collect::<Vec<_>>();
+                let mut scalar = FftPlan::<f32>::with_kernel( n, direction, kernel );
+                scalar.set_isa( Isa::Scalar ).expect("scalar always supported");
+                let mut expect = arr.clone();
+                scalar.process( &mut expect[..] ).expect("plan length mismatch");
+                for isa in fft_simd::available() {
+                    let mut plan = FftPlan::<f32>::with_kernel( n, direction, kernel );
+                    plan.set_isa( isa ).expect("isa reported available");
+                    assert_eq!( plan.isa(), isa );
+                    let mut out = arr.clone();
+                    plan.process( &mut out[..] ).expect("plan length mismatch");
+                    out.iter().zip( expect.iter() )
+                        .for_each( |x| assert!( nearly_equal( &x.0.re, &x.1.re, &1e-3 ) &&
+                                                nearly_equal( &x.0.im, &x.1.im, &1e-3 ) ) );
+
+                    let arr64 = arr.iter().map( |x| complex::Complex::new( x.re as f64, x.im as f64 ) ).collect::<Vec<_>>();
+                    let mut scalar64 = FftPlan::<f64>::with_kernel( n, direction, kernel );
+                    scalar64.set_isa( Isa::Scalar ).expect("scalar always supported");
+                    let mut plan64 = FftPlan::<f64>::with_kernel( n, direction, kernel );
+                    plan64.set_isa( isa ).expect("isa reported available");
+                    let mut expect64 = arr64.clone();
+                    let mut out64 = arr64.clone();
+                    scalar64.process( &mut expect64[..] ).expect("plan length mismatch");
+                    plan64.process( &mut out64[..] ).expect("plan length mismatch");
+                    out64.iter().zip( expect64.iter() )
+                        .for_each( |x| assert!( nearly_equal( &x.0.re, &x.1.re, &1e-10 ) &&
+                                                nearly_equal( &x.0.im, &x.1.im, &1e-10 ) ) );
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn test_large_transforms_parallel_by_default() {
+    assert_eq!( FftOptions::default().threads, 0 );
+    let n = PARALLEL_MIN_LEN;
+    let arr = test_signal::<f64>( n ).iter().map( |x| complex::Complex::new( *x, 0. ) ).collect::<Vec<_>>();
+    let single = planned_threaded( &arr[..], false, 1 );
+    for out in [ cooley_tukey_radix2_dit( &arr[..], false ), cooley_tukey_radix2_dif( &arr[..], false ) ].iter() {
+        out.iter().zip( single.iter() )
+            .for_each( |x| assert!( nearly_equal( &x.0.re, &x.1.re, &1e-6 ) &&
+                                    nearly_equal( &x.0.im, &x.1.im, &1e-6 ) ) );
+    }
+}
@@ -0,0 +1,223 @@
+//!four-step/six-step decomposition of large transforms across std::thread workers
+
+extern crate num;
+
+use self::num::complex;
+use std::thread;
+
+use fft::{ self, Direction, FftFloat, FftPlan };
+use error;
+
+#[cfg(test)]
+use test_common::{ nearly_equal, noise_complex };
+
+///transforms from this length up are split across threads by default
+pub const PARALLEL_MIN_LEN : usize = 1 << 20;
+
+///edge of the square tiles transposes copy through, sized to keep a tile of both matrices in l1
+const TILE : usize = 32;
+
+///worker count used when 0 threads are requested
+pub fn available_threads() -> usize {
+    thread::available_parallelism().map( |x| x.get() ).unwrap_or( 1 )
+}
+
+#[derive(Debug, Clone)]
+enum Stages<T: FftFloat> {
+    ///n has no factor pair, run on the calling thread
+    Single( FftPlan<T> ),
+    SixStep {
+        cols: FftPlan<T>,
+        rows: FftPlan<T>,
+        ///w_n^k for k < n
+        twiddles: Vec<complex::Complex<T>>,
+    },
+}
+
+///precomputed sub-plans and twiddles for a transform of length n = n1 * n2 run as
+///n2 transforms of length n1, a twiddle multiply, and n1 transforms of length n2, each stage split over threads
+#[derive(Debug, Clone)]
+pub struct ParallelFftPlan<T: FftFloat> {
+    n: usize,
+    n1: usize,
+    n2: usize,
+    threads: usize,
+    direction: Direction,
+    stages: Stages<T>,
+}
+
+impl<T: FftFloat> ParallelFftPlan<T> {
+
+    ///threads = 0 uses every available core. lengths without a factor pair, primes among them,
+    ///run as a plain FftPlan on the calling thread
+    pub fn new( n: usize, direction: Direction, threads: usize ) -> ParallelFftPlan<T> {
+        let threads = if threads == 0 { available_threads() } else { threads };
+        //largest factor not above sqrt(n) keeps both sub-transforms near sqrt(n) long
+        let mut n1 = 1;
+        let mut d = 2;
+        while d * d <= n {
+            if n % d == 0 {
+                n1 = d;
+            }
+            d += 1;
+        }
+        let n2 = if n == 0 { 0 } else { n / n1 };
+        let stages = if n1 == 1 {
+            Stages::Single( FftPlan::new( n, direction ) )
+        } else {
+            Stages::SixStep {
+                cols: FftPlan::new( n1, direction ),
+                rows: FftPlan::new( n2, direction ),
+                twiddles: (0..n).map( |k| fft::twiddle( k, n, direction == Direction::Inverse ) ).collect(),
+            }
+        };
+        ParallelFftPlan {
+            n,
+            n1,
+            n2,
+            threads,
+            direction,
+            stages,
+        }
+    }
+
+    pub fn len( &self ) -> usize {
+        self.n
+    }
+
+    pub fn is_empty( &self ) -> bool {
+        self.n == 0
+    }
+
+    pub fn direction( &self ) -> Direction {
+        self.direction
+    }
+
+    pub fn threads( &self ) -> usize {
+        self.threads
+    }
+
+    ///(n1, n2) with n = n1 * n2 and n1 <= n2
+    pub fn factors( &self ) -> ( usize, usize ) {
+        ( self.n1, self.n2 )
+    }
+
+    ///transforms buf in place, buf length must equal the planned length
+    pub fn process( &self, buf: &mut [complex::Complex<T>] ) -> Result< (), error::Error > {
+        if buf.len() != self.n {
+            Err( error::Error::Dimension )
+        } else {
+            self.run( buf );
+            Ok( () )
+        }
+    }
+
+    pub(crate) fn run( &self, buf: &mut [complex::Complex<T>] ) {
+        let ( n1, n2 ) = ( self.n1, self.n2 );
+        let ( cols, rows, twiddles ) = match self.stages {
+            Stages::Single( ref plan ) => return plan.run( buf ),
+            Stages::SixStep { ref cols, ref rows, ref twiddles } => ( cols, rows, twiddles ),
+        };
+        //X[k1 + n1*k2] = sum_j2 w_n2^(j2*k2) * w_n^(j2*k1) * sum_j1 w_n1^(j1*k1) * x[j1*n2 + j2]
+        let mut scratch = vec![ complex::Complex::new( T::zero(), T::zero() ); self.n ];
+        //columns of the n1 x n2 input become contiguous rows
+        transpose( buf, &mut scratch[..], n1, n2, self.threads );
+        for_rows( &mut scratch[..], n1, self.threads, |j2, row| {
+            cols.run( row );
+            for (k1, x) in row.iter_mut().enumerate() {
+                *x *= twiddles[ j2 * k1 ];
+            }
+        } );
+        transpose( &scratch[..], buf, n2, n1, self.threads );
+        for_rows( buf, n2, self.threads, |_, row| rows.run( row ) );
+        //rows indexed by k1 hold k2, output order is k2 major
+        transpose( buf, &mut scratch[..], n1, n2, self.threads );
+        buf.copy_from_slice( &scratch[..] );
+    }
+}
+
+///calls f( row index, row ) for each row of length row_len, rows split into one contiguous run per thread
+fn for_rows<T, F>( buf: &mut [T], row_len: usize, threads: usize, f: F )
+    where T: Send, F: Fn( usize, &mut [T] ) + Sync {
+    let rows = buf.len() / row_len;
+    let threads = threads.max( 1 );
+    let per_thread = ( rows + threads - 1 ) / threads;
+    if per_thread >= rows {
+        for (i, row) in buf.chunks_mut( row_len ).enumerate() {
+            f( i, row );
+        }
+        return
+    }
+    let f = &f;
+    thread::scope( |s| {
+        for (c, chunk) in buf.chunks_mut( per_thread * row_len ).enumerate() {
+            s.spawn( move || {
+                for (i, row) in chunk.chunks_mut( row_len ).enumerate() {
+                    f( c * per_thread + i, row );
+                }
+            } );
+        }
+    } );
+}
+
+///dst (cols x rows) = transpose of src (rows x cols), output rows split over threads
+fn transpose<T: Copy + Send + Sync>( src: &[T], dst: &mut [T], rows: usize, cols: usize, threads: usize ) {
+    let threads = threads.max( 1 );
+    let per_thread = ( cols + threads - 1 ) / threads;
+    let copy_tiles = |c_start: usize, out: &mut [T]| {
+        let c_end = c_start + out.len() / rows;
+        for cb in (c_start..c_end).step_by( TILE ) {
+            for rb in (0..rows).step_by( TILE ) {
+                for c in cb..( cb + TILE ).min( c_end ) {
+                    let out_row = &mut out[ ( c - c_start ) * rows .. ( c - c_start + 1 ) * rows ];
+                    for (r, y) in out_row.iter_mut().enumerate().take( ( rb + TILE ).min( rows ) ).skip( rb ) {
+                        *y = src[ r * cols + c ];
+                    }
+                }
+            }
+        }
+    };
+    if per_thread >= cols {
+        return copy_tiles( 0, dst )
+    }
+    let copy_tiles = &copy_tiles;
+    thread::scope( |s| {
+        for (i, chunk) in dst.chunks_mut( per_thread * rows ).enumerate() {
+            s.spawn( move || copy_tiles( i * per_thread, chunk ) );
+        }
+    } );
+}
+
+#[test]
+fn test_parallel_matches_single_threaded() {
+    //small lengths cover the decomposition: square and non-square powers of 2, mixed radix, prime
+    let cases = [ 1, 2, 64, 128, 6720, 1009 ].iter().map( |&n| ( n, vec![ 1, 3, 4 ] ) )
+        .chain( Some( ( PARALLEL_MIN_LEN, vec![ 4 ] ) ) );
+    for ( n, thread_counts ) in cases {
+        let arr = noise_complex( n, n as u64 );
+        for &direction in [ Direction::Forward, Direction::Inverse ].iter() {
+            let mut expect = arr.clone();
+            FftPlan::new( n, direction ).process( &mut expect[..] ).expect("plan length mismatch");
+            for &threads in thread_counts.iter() {
+                let plan = ParallelFftPlan::new( n, direction, threads );
+                assert_eq!( plan.factors().0 * plan.factors().1, n );
+                let mut out = arr.clone();
+                plan.process( &mut out[..] ).expect("plan length mismatch");
+                let tol = 1e-9 * ( n as f64 ).sqrt().max( 1. );
+                out.iter().zip( expect.iter() )
+                    .for_each( |x| assert!( nearly_equal( &x.0.re, &x.1.re, &tol ) &&
+                                            nearly_equal( &x.0.im, &x.1.im, &tol ) ) );
+            }
+        }
+    }
+}
+
+#[test]
+fn test_parallel_length_mismatch() {
+    let plan = ParallelFftPlan::<f32>::new( 64, Direction::Forward, 2 );
+    let mut buf = vec![ complex::Complex::new( 0f32, 0f32 ); 32 ];
+    assert!( plan.process( &mut buf[..] ).is_err() );
+    assert_eq!( plan.factors(), ( 8, 8 ) );
+    assert_eq!( ParallelFftPlan::<f32>::new( 1009, Direction::Forward, 2 ).factors(), ( 1, 1009 ) );
+    assert_eq!( ParallelFftPlan::<f32>::new( 64, Direction::Forward, 0 ).threads(), available_threads() );
+}
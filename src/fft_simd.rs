@@ -0,0 +1,357 @@
+//!runtime selected x86_64 vector butterflies for the power of 2 fft kernels
+
+extern crate num;
+
+use self::num::complex;
+
+use fft::FftFloat;
+
+#[cfg(test)]
+use test_common::{ nearly_equal, noise_complex };
+
+///instruction set used for butterflies
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Isa {
+    Scalar,
+    ///2 f32 or 1 f64 complex per register
+    Sse2,
+    ///4 f32 or 2 f64 complex per register
+    Avx,
+    ///Avx with fused multiply add/subtract for the complex products
+    AvxFma,
+}
+
+///fastest instruction set available on the running cpu
+pub(crate) fn detect() -> Isa {
+    #[cfg(target_arch = "x86_64")]
+    {
+        if is_x86_feature_detected!( "avx" ) && is_x86_feature_detected!( "fma" ) {
+            return Isa::AvxFma
+        }
+        if is_x86_feature_detected!( "avx" ) {
+            return Isa::Avx
+        }
+        if is_x86_feature_detected!( "sse2" ) {
+            return Isa::Sse2
+        }
+    }
+    Isa::Scalar
+}
+
+///whether the running cpu can execute butterflies for isa
+pub(crate) fn is_supported( isa: Isa ) -> bool {
+    match isa {
+        Isa::Scalar => true,
+        #[cfg(target_arch = "x86_64")]
+        Isa::Sse2 => is_x86_feature_detected!( "sse2" ),
+        #[cfg(target_arch = "x86_64")]
+        Isa::Avx => is_x86_feature_detected!( "avx" ),
+        #[cfg(target_arch = "x86_64")]
+        Isa::AvxFma => is_x86_feature_detected!( "avx" ) && is_x86_feature_detected!( "fma" ),
+        #[cfg(not(target_arch = "x86_64"))]
+        _ => false,
+    }
+}
+
+///all instruction sets the running cpu supports, Scalar first
+#[cfg(test)]
+pub(crate) fn available() -> Vec<Isa> {
+    [ Isa::Scalar, Isa::Sse2, Isa::Avx, Isa::AvxFma ].iter().cloned().filter( |x| is_supported( *x ) ).collect()
+}
+
+///radix-2 butterflies over group = [lo, hi] with |hi| = |tw|: lo' = lo + tw*hi, hi' = lo - tw*hi
+fn butterfly2_scalar<T: FftFloat>( group: &mut [complex::Complex<T>], tw: &[complex::Complex<T>] ) {
+    butterfly2_tail( group, tw, 0 );
+}
+
+///radix-2^2 butterflies over group = [q0, q1, q2, q3] with |q| = |tw[i]|,
+///q1, q2, q3 are scaled by w^2j, w^j, w^3j and rot = w^l is +i for inverse, -i otherwise
+fn butterfly4_scalar<T: FftFloat>( group: &mut [complex::Complex<T>], tw: [ &[complex::Complex<T>]; 3 ], inverse: bool ) {
+    butterfly4_tail( group, tw, inverse, 0 );
+}
+
+fn butterfly2_tail<T: FftFloat>( group: &mut [complex::Complex<T>], tw: &[complex::Complex<T>], start: usize ) {
+    let h = tw.len();
+    let ( lo, hi ) = group.split_at_mut( h );
+    for ((x, y), w) in lo.iter_mut().zip( hi.iter_mut() ).zip( tw.iter() ).skip( start ) {
+        let even = *x;
+        let odd = *w * *y;
+        *x = even + odd;
+        *y = even - odd;
+    }
+}
+
+fn butterfly4_tail<T: FftFloat>( group: &mut [complex::Complex<T>], tw: [ &[complex::Complex<T>]; 3 ], inverse: bool, start: usize ) {
+    let l = tw[0].len();
+    let rot = if inverse {
+        complex::Complex::new( T::zero(), T::one() )
+    } else {
+        complex::Complex::new( T::zero(), -T::one() )
+    };
+    for j in start..l {
+        let a = group[j];
+        let b = tw[1][j] * group[ j + l ];
+        let c = tw[0][j] * group[ j + 2 * l ];
+        let d = tw[2][j] * group[ j + 3 * l ];
+        let ( s0, s1 ) = ( a + b, a - b );
+        let ( s2, s3 ) = ( c + d, rot * ( c - d ) );
+        group[j] = s0 + s2;
+        group[ j + l ] = s1 + s3;
+        group[ j + 2 * l ] = s0 - s2;
+        group[ j + 3 * l ] = s1 - s3;
+    }
+}
+
+///complex per register for each instruction set and element size, halves narrower than this stay scalar
+fn lanes( isa: Isa, f64_elem: bool ) -> usize {
+    match ( isa, f64_elem ) {
+        ( Isa::Scalar, _ ) => usize::MAX,
+        ( Isa::Sse2, false ) => 2,
+        ( Isa::Sse2, true ) => 1,
+        ( _, false ) => 4,
+        ( _, true ) => 2,
+    }
+}
+
+///per element type butterflies FftFloat requires, in a crate private module so only plans,
+///which validate the isa and size every group from their own tables, reach the unsafe kernels
+pub(crate) mod sealed {
+    use super::complex;
+    use super::Isa;
+
+    pub trait Butterflies : Sized {
+        ///radix-2 butterflies as butterfly2_scalar with isa, which must be supported by the cpu
+        fn butterfly2( isa: Isa, group: &mut [complex::Complex<Self>], tw: &[complex::Complex<Self>] );
+        ///radix-2^2 butterflies as butterfly4_scalar with isa, which must be supported by the cpu
+        fn butterfly4( isa: Isa, group: &mut [complex::Complex<Self>], tw: [ &[complex::Complex<Self>]; 3 ], inverse: bool );
+    }
+}
+
+macro_rules! dispatch {
+    ( $ty:ty, $f64_elem:expr,
+      $sse2_2:ident, $sse2_4:ident, $avx_2:ident, $avx_4:ident, $fma_2:ident, $fma_4:ident ) => {
+
+        impl sealed::Butterflies for $ty {
+
+            fn butterfly2( isa: Isa, group: &mut [complex::Complex<$ty>], tw: &[complex::Complex<$ty>] ) {
+                if tw.len() < lanes( isa, $f64_elem ) {
+                    return butterfly2_scalar( group, tw )
+                }
+                #[cfg(target_arch = "x86_64")]
+                {
+                    let done = unsafe {
+                        match isa {
+                            Isa::Sse2 => x86::$sse2_2( group, tw ),
+                            Isa::Avx => x86::$avx_2( group, tw ),
+                            Isa::AvxFma => x86::$fma_2( group, tw ),
+                            Isa::Scalar => 0,
+                        }
+                    };
+                    butterfly2_tail( group, tw, done );
+                }
+            }
+
+            fn butterfly4( isa: Isa, group: &mut [complex::Complex<$ty>], tw: [ &[complex::Complex<$ty>]; 3 ], inverse: bool ) {
+                if tw[0].len() < lanes( isa, $f64_elem ) {
+                    return butterfly4_scalar( group, tw, inverse )
+                }
+                #[cfg(target_arch = "x86_64")]
+                {
+                    let done = unsafe {
+                        match isa {
+                            Isa::Sse2 => x86::$sse2_4( group, tw, inverse ),
+                            Isa::Avx => x86::$avx_4( group, tw, inverse ),
+                            Isa::AvxFma => x86::$fma_4( group, tw, inverse ),
+                            Isa::Scalar => 0,
+                        }
+                    };
+                    butterfly4_tail( group, tw, inverse, done );
+                }
+            }
+        }
+    }
+}
+
+dispatch!( f32, false, sse2_2_f32, sse2_4_f32, avx_2_f32, avx_4_f32, fma_2_f32, fma_4_f32 );
+dispatch!( f64, true, sse2_2_f64, sse2_4_f64, avx_2_f64, avx_4_f64, fma_2_f64, fma_4_f64 );
+
+#[cfg(target_arch = "x86_64")]
+mod x86 {
+    //complex numbers are #[repr(C)] (re, im) pairs so slices load directly as interleaved lanes,
+    //each kernel handles whole registers and returns the index the scalar tail continues from
+
+    use std::arch::x86_64::*;
+    use super::complex::Complex;
+
+    //(ar*wr - ai*wi, ai*wr + ar*wi) per complex lane
+    macro_rules! cmul_sse2_ps {
+        ( $a:expr, $w:expr ) => {{
+            let ( a, w ) = ( $a, $w );
+            let wr = _mm_shuffle_ps( w, w, 0xA0 );
+            let wi = _mm_shuffle_ps( w, w, 0xF5 );
+            let a_swap = _mm_shuffle_ps( a, a, 0xB1 );
+            let sign = _mm_set_ps( 0., -0., 0., -0. );
+            _mm_add_ps( _mm_mul_ps( a, wr ), _mm_xor_ps( _mm_mul_ps( a_swap, wi ), sign ) )
+        }}
+    }
+
+    macro_rules! cmul_sse2_pd {
+        ( $a:expr, $w:expr ) => {{
+            let ( a, w ) = ( $a, $w );
+            let wr = _mm_unpacklo_pd( w, w );
+            let wi = _mm_unpackhi_pd( w, w );
+            let a_swap = _mm_shuffle_pd( a, a, 1 );
+            let sign = _mm_set_pd( 0., -0. );
+            _mm_add_pd( _mm_mul_pd( a, wr ), _mm_xor_pd( _mm_mul_pd( a_swap, wi ), sign ) )
+        }}
+    }
+
+    macro_rules! cmul_avx_ps {
+        ( $a:expr, $w:expr ) => {{
+            let ( a, w ) = ( $a, $w );
+            let a_swap = _mm256_permute_ps( a, 0xB1 );
+            _mm256_addsub_ps( _mm256_mul_ps( a, _mm256_moveldup_ps( w ) ), _mm256_mul_ps( a_swap, _mm256_movehdup_ps( w ) ) )
+        }}
+    }
+
+    macro_rules! cmul_avx_pd {
+        ( $a:expr, $w:expr ) => {{
+            let ( a, w ) = ( $a, $w );
+            let a_swap = _mm256_permute_pd( a, 0x5 );
+            _mm256_addsub_pd( _mm256_mul_pd( a, _mm256_movedup_pd( w ) ), _mm256_mul_pd( a_swap, _mm256_permute_pd( w, 0xF ) ) )
+        }}
+    }
+
+    macro_rules! cmul_fma_ps {
+        ( $a:expr, $w:expr ) => {{
+            let ( a, w ) = ( $a, $w );
+            let a_swap = _mm256_permute_ps( a, 0xB1 );
+            _mm256_fmaddsub_ps( a, _mm256_moveldup_ps( w ), _mm256_mul_ps( a_swap, _mm256_movehdup_ps( w ) ) )
+        }}
+    }
+
+    macro_rules! cmul_fma_pd {
+        ( $a:expr, $w:expr ) => {{
+            let ( a, w ) = ( $a, $w );
+            let a_swap = _mm256_permute_pd( a, 0x5 );
+            _mm256_fmaddsub_pd( a, _mm256_movedup_pd( w ), _mm256_mul_pd( a_swap, _mm256_permute_pd( w, 0xF ) ) )
+        }}
+    }
+
+    //(re, im) -> (im, re) in every complex lane
+    macro_rules! swap_sse2_ps { ( $a:expr ) => {{ let a = $a; _mm_shuffle_ps( a, a, 0xB1 ) }} }
+    macro_rules! swap_sse2_pd { ( $a:expr ) => {{ let a = $a; _mm_shuffle_pd( a, a, 1 ) }} }
+    macro_rules! swap_avx_ps { ( $a:expr ) => { _mm256_permute_ps( $a, 0xB1 ) } }
+    macro_rules! swap_avx_pd { ( $a:expr ) => { _mm256_permute_pd( $a, 0x5 ) } }
+
+    //sign bits to flip the real and imaginary part of every complex lane
+    macro_rules! flip_sse2_ps { ( $re:expr, $im:expr ) => { _mm_set_ps( $im, $re, $im, $re ) } }
+    macro_rules! flip_sse2_pd { ( $re:expr, $im:expr ) => { _mm_set_pd( $im, $re ) } }
+    macro_rules! flip_avx_ps { ( $re:expr, $im:expr ) => { _mm256_set_ps( $im, $re, $im, $re, $im, $re, $im, $re ) } }
+    macro_rules! flip_avx_pd { ( $re:expr, $im:expr ) => { _mm256_set_pd( $im, $re, $im, $re ) } }
+
+    macro_rules! kernels {
+        ( $feature:tt, $radix2:ident, $radix4:ident, $ty:ty, $lanes:expr,
+          $load:ident, $store:ident, $add:ident, $sub:ident, $xor:ident, $cmul:ident, $swap:ident, $flip:ident ) => {
+
+            #[target_feature(enable = $feature)]
+            pub unsafe fn $radix2( group: &mut [Complex<$ty>], tw: &[Complex<$ty>] ) -> usize {
+                let h = tw.len();
+                assert!( group.len() == 2 * h );
+                let p = group.as_mut_ptr() as *mut $ty;
+                let t = tw.as_ptr() as *const $ty;
+                let mut j = 0;
+                for _ in 0..h / $lanes {
+                    let a = $load( p.add( 2 * j ) );
+                    let b = $cmul!( $load( p.add( 2 * ( j + h ) ) ), $load( t.add( 2 * j ) ) );
+                    $store( p.add( 2 * j ), $add( a, b ) );
+                    $store( p.add( 2 * ( j + h ) ), $sub( a, b ) );
+                    j += $lanes;
+                }
+                j
+            }
+
+            #[target_feature(enable = $feature)]
+            pub unsafe fn $radix4( group: &mut [Complex<$ty>], tw: [ &[Complex<$ty>]; 3 ], inverse: bool ) -> usize {
+                let l = tw[0].len();
+                assert!( group.len() == 4 * l && tw[1].len() == l && tw[2].len() == l );
+                let p = group.as_mut_ptr() as *mut $ty;
+                let ( t1, t2, t3 ) = ( tw[0].as_ptr() as *const $ty, tw[1].as_ptr() as *const $ty, tw[2].as_ptr() as *const $ty );
+                //times i is (-im, re) and times -i is (im, -re)
+                let flip = if inverse { $flip!( -0., 0. ) } else { $flip!( 0., -0. ) };
+                let mut j = 0;
+                for _ in 0..l / $lanes {
+                    let a = $load( p.add( 2 * j ) );
+                    let b = $cmul!( $load( p.add( 2 * ( j + l ) ) ), $load( t2.add( 2 * j ) ) );
+                    let c = $cmul!( $load( p.add( 2 * ( j + 2 * l ) ) ), $load( t1.add( 2 * j ) ) );
+                    let d = $cmul!( $load( p.add( 2 * ( j + 3 * l ) ) ), $load( t3.add( 2 * j ) ) );
+                    let ( s0, s1 ) = ( $add( a, b ), $sub( a, b ) );
+                    let ( s2, s3 ) = ( $add( c, d ), $xor( $swap!( $sub( c, d ) ), flip ) );
+                    $store( p.add( 2 * j ), $add( s0, s2 ) );
+                    $store( p.add( 2 * ( j + l ) ), $add( s1, s3 ) );
+                    $store( p.add( 2 * ( j + 2 * l ) ), $sub( s0, s2 ) );
+                    $store( p.add( 2 * ( j + 3 * l ) ), $sub( s1, s3 ) );
+                    j += $lanes;
+                }
+                j
+            }
+        }
+    }
+
+    kernels!( "sse2", sse2_2_f32, sse2_4_f32, f32, 2, _mm_loadu_ps, _mm_storeu_ps, _mm_add_ps, _mm_sub_ps, _mm_xor_ps, cmul_sse2_ps, swap_sse2_ps, flip_sse2_ps );
+    kernels!( "sse2", sse2_2_f64, sse2_4_f64, f64, 1, _mm_loadu_pd, _mm_storeu_pd, _mm_add_pd, _mm_sub_pd, _mm_xor_pd, cmul_sse2_pd, swap_sse2_pd, flip_sse2_pd );
+    kernels!( "avx", avx_2_f32, avx_4_f32, f32, 4, _mm256_loadu_ps, _mm256_storeu_ps, _mm256_add_ps, _mm256_sub_ps, _mm256_xor_ps, cmul_avx_ps, swap_avx_ps, flip_avx_ps );
+    kernels!( "avx", avx_2_f64, avx_4_f64, f64, 2, _mm256_loadu_pd, _mm256_storeu_pd, _mm256_add_pd, _mm256_sub_pd, _mm256_xor_pd, cmul_avx_pd, swap_avx_pd, flip_avx_pd );
+    kernels!( "avx,fma", fma_2_f32, fma_4_f32, f32, 4, _mm256_loadu_ps, _mm256_storeu_ps, _mm256_add_ps, _mm256_sub_ps, _mm256_xor_ps, cmul_fma_ps, swap_avx_ps, flip_avx_ps );
+    kernels!( "avx,fma", fma_2_f64, fma_4_f64, f64, 2, _mm256_loadu_pd, _mm256_storeu_pd, _mm256_add_pd, _mm256_sub_pd, _mm256_xor_pd, cmul_fma_pd, swap_avx_pd, flip_avx_pd );
+}
+
+#[test]
+fn test_butterflies_match_scalar() {
+    //halves of 13 exercise full registers plus a scalar tail for every width
+    for &h in [ 1, 2, 4, 13 ].iter() {
+        let data = noise_complex( 4 * h, 7 );
+        let tw = noise_complex( 3 * h, 11 );
+        let tw3 = [ &tw[..h], &tw[h..2*h], &tw[2*h..] ];
+        for isa in available() {
+            for &inverse in [ false, true ].iter() {
+                let mut expect = data.clone();
+                butterfly4_scalar( &mut expect[..], tw3, inverse );
+                let mut actual = data.clone();
+                <f64 as sealed::Butterflies>::butterfly4( isa, &mut actual[..], tw3, inverse );
+                for (x, y) in actual.iter().zip( expect.iter() ) {
+                    assert!( nearly_equal( &x.re, &y.re, &1e-12 ) && nearly_equal( &x.im, &y.im, &1e-12 ), "{:?} radix4 f64", isa );
+                }
+
+                let data32 = data.iter().map( |x| complex::Complex::new( x.re as f32, x.im as f32 ) ).collect::<Vec<_>>();
+                let tw32 = tw.iter().map( |x| complex::Complex::new( x.re as f32, x.im as f32 ) ).collect::<Vec<_>>();
+                let tw32_3 = [ &tw32[..h], &tw32[h..2*h], &tw32[2*h..] ];
+                let mut expect = data32.clone();
+                butterfly4_scalar( &mut expect[..], tw32_3, inverse );
+                let mut actual = data32.clone();
+                <f32 as sealed::Butterflies>::butterfly4( isa, &mut actual[..], tw32_3, inverse );
+                for (x, y) in actual.iter().zip( expect.iter() ) {
+                    assert!( nearly_equal( &x.re, &y.re, &1e-5 ) && nearly_equal( &x.im, &y.im, &1e-5 ), "{:?} radix4 f32", isa );
+                }
+            }
+
+            let mut expect = data[..2*h].to_vec();
+            butterfly2_scalar( &mut expect[..], &tw[..h] );
+            let mut actual = data[..2*h].to_vec();
+            <f64 as sealed::Butterflies>::butterfly2( isa, &mut actual[..], &tw[..h] );
+            for (x, y) in actual.iter().zip( expect.iter() ) {
+                assert!( nearly_equal( &x.re, &y.re, &1e-12 ) && nearly_equal( &x.im, &y.im, &1e-12 ), "{:?} radix2 f64", isa );
+            }
+
+            let data32 = data[..2*h].iter().map( |x| complex::Complex::new( x.re as f32, x.im as f32 ) ).collect::<Vec<_>>();
+            let tw32 = tw[..h].iter().map( |x| complex::Complex::new( x.re as f32, x.im as f32 ) ).collect::<Vec<_>>();
+            let mut expect = data32.clone();
+            butterfly2_scalar( &mut expect[..], &tw32[..] );
+            let mut actual = data32.clone();
+            <f32 as sealed::Butterflies>::butterfly2( isa, &mut actual[..], &tw32[..] );
+            for (x, y) in actual.iter().zip( expect.iter() ) {
+                assert!( nearly_equal( &x.re, &y.re, &1e-5 ) && nearly_equal( &x.im, &y.im, &1e-5 ), "{:?} radix2 f32", isa );
+            }
+        }
+    }
+}
@@ -0,0 +1,209 @@
+///Single bin trackers: block Goertzel at arbitrary frequency and per-sample sliding DFT
+extern crate num;
+
+use self::num::complex;
+
+use error;
+use fft::FftFloat;
+
+#[cfg(test)]
+use test_common::{ nearly_equal, test_signal };
+
+fn from_f64<T: FftFloat>( x: f64 ) -> T {
+    T::from( x ).unwrap()
+}
+
+///generalized Goertzel detector returning X(f) = sum_n x(n) e^(-2pi*i*f*n/fs) over each block,
+///f need not fall on a bin of the block length
+#[derive(Debug, Clone)]
+pub struct Goertzel<T: FftFloat> {
+    coeff: T,
+    rotation: complex::Complex<T>,
+    block_rotation: complex::Complex<T>,
+    block_len: usize,
+    count: usize,
+    s1: T,
+    s2: T,
+}
+
+impl<T: FftFloat> Goertzel<T> {
+
+    pub fn new( freq: T, fs: T, block_len: usize ) -> Result< Goertzel<T>, error::Error > {
+        if block_len == 0 || fs <= T::zero() {
+            return Err( error::Error::DataInvalid )
+        }
+        let omega = 2. * ::std::f64::consts::PI * freq.to_f64().unwrap() / fs.to_f64().unwrap();
+        //phase of the block end reduced in f64 to keep long blocks accurate
+        let block_phase = ( -omega * block_len as f64 ) % ( 2. * ::std::f64::consts::PI );
+        Ok( Goertzel {
+            coeff: from_f64( 2. * omega.cos() ),
+            rotation: complex::Complex::new( from_f64( omega.cos() ), from_f64( omega.sin() ) ),
+            block_rotation: complex::Complex::new( from_f64( block_phase.cos() ), from_f64( block_phase.sin() ) ),
+            block_len,
+            count: 0,
+            s1: T::zero(),
+            s2: T::zero(),
+        })
+    }
+
+    pub fn reset( &mut self ) {
+        self.count = 0;
+        self.s1 = T::zero();
+        self.s2 = T::zero();
+    }
+
+    ///feeds one sample, returns the block's spectral value once block_len samples are in
+    pub fn push( &mut self, x: T ) -> Option< complex::Complex<T> > {
+        let s0 = x + self.coeff * self.s1 - self.s2;
+        self.s2 = self.s1;
+        self.s1 = s0;
+        self.count += 1;
+        if self.count < self.block_len {
+            return None
+        }
+        //y = e^(i*w) s(N-1) - s(N-2), then undo the phase advance of N samples
+        let y = self.rotation * self.s1 - complex::Complex::new( self.s2, T::zero() );
+        let ret = y * self.block_rotation;
+        self.reset();
+        Some( ret )
+    }
+
+    ///values of every block completed within the input
+    pub fn process( &mut self, input: &[T] ) -> Vec< complex::Complex<T> > {
+        input.iter().filter_map( |x| self.push( *x ) ).collect()
+    }
+}
+
+///sliding DFT over the most recent window_len samples, updating each tracked bin in O(1) per sample.
+///bins are recomputed directly once per window to stop rounding drift from accumulating
+#[derive(Debug, Clone)]
+pub struct SlidingDft<T: FftFloat> {
+    bins: Vec<usize>,
+    rotations: Vec< complex::Complex<T> >,
+    values: Vec< complex::Complex<T> >,
+    history: Vec<T>,
+    pos: usize,
+    since_sync: usize,
+}
+
+impl<T: FftFloat> SlidingDft<T> {
+
+    pub fn new( window_len: usize, bins: &[usize] ) -> Result< SlidingDft<T>, error::Error > {
+        if window_len == 0 {
+            return Err( error::Error::DataInvalid )
+        }
+        if bins.iter().any( |&k| k >= window_len ) {
+            return Err( error::Error::Dimension )
+        }
+        let rotations = bins.iter().map( |&k| {
+            let angle = 2. * ::std::f64::consts::PI * k as f64 / window_len as f64;
+            complex::Complex::new( from_f64( angle.cos() ), from_f64( angle.sin() ) )
+        }).collect();
+        Ok( SlidingDft {
+            bins: bins.to_vec(),
+            rotations,
+            values: vec![ complex::Complex::new( T::zero(), T::zero() ); bins.len() ],
+            history: vec![ T::zero(); window_len ],
+            pos: 0,
+            since_sync: 0,
+        })
+    }
+
+    pub fn bins( &self ) -> &[usize] {
+        &self.bins[..]
+    }
+
+    ///current values of the tracked bins, with the oldest sample of the window at time index 0
+    pub fn values( &self ) -> &[ complex::Complex<T> ] {
+        &self.values[..]
+    }
+
+    fn resync( &mut self ) {
+        let n = self.history.len();
+        for (k, v) in self.bins.iter().zip( self.values.iter_mut() ) {
+            let mut acc = complex::Complex::new( T::zero(), T::zero() );
+            for m in 0..n {
+                let x = self.history[ ( self.pos + m ) % n ];
+                let angle = -2. * ::std::f64::consts::PI * ( ( k * m ) % n ) as f64 / n as f64;
+                acc += complex::Complex::new( from_f64( angle.cos() ), from_f64( angle.sin() ) ) * x;
+            }
+            *v = acc;
+        }
+    }
+
+    ///X_k(n) = ( X_k(n-1) - x(n-N) + x(n) ) * e^(2pi*i*k/N)
+    pub fn push( &mut self, x: T ) -> &[ complex::Complex<T> ] {
+        let n = self.history.len();
+        let oldest = self.history[ self.pos ];
+        self.history[ self.pos ] = x;
+        self.pos = ( self.pos + 1 ) % n;
+        self.since_sync += 1;
+        if self.since_sync == n {
+            self.since_sync = 0;
+            self.resync();
+        } else {
+            let delta = x - oldest;
+            for (v, r) in self.values.iter_mut().zip( self.rotations.iter() ) {
+                *v = ( *v + delta ) * r;
+            }
+        }
+        &self.values[..]
+    }
+}
+
+#[cfg(test)]
+fn dtft( x: &[f64], freq: f64, fs: f64 ) -> complex::Complex<f64> {
+    x.iter().enumerate().fold( complex::Complex::new( 0., 0. ), |acc, (n, v)| {
+        let angle = -2. * ::std::f64::consts::PI * freq * n as f64 / fs;
+        acc + complex::Complex::from_polar( v, &angle )
+    })
+}
+
+#[test]
+fn test_goertzel_arbitrary_frequency() {
+    let fs = 8000.;
+    let x = test_signal::<f64>( 1000 );
+    for &freq in [ 697., 1209.5, 0., 3999.9 ].iter() {
+        let mut g = Goertzel::new( freq, fs, 250 ).expect("goertzel init failed");
+        let out = g.process( &x );
+        assert_eq!( out.len(), 4 );
+        for (b, v) in out.iter().enumerate() {
+            let expected = dtft( &x[ b * 250 .. ( b + 1 ) * 250 ], freq, fs );
+            assert!( nearly_equal( &v.re, &expected.re, &1e-8 ) && nearly_equal( &v.im, &expected.im, &1e-8 ) );
+        }
+    }
+}
+
+#[test]
+fn test_goertzel_tone_detection() {
+    //dtmf style detection of 770 Hz against a neighbouring 852 Hz filter
+    let fs = 8000.;
+    let x = (0..205).map( |i| ( 2. * ::std::f64::consts::PI * 770. * i as f64 / fs ).sin() ).collect::<Vec<_>>();
+    let mut hit = Goertzel::new( 770., fs, 205 ).expect("goertzel init failed");
+    let mut miss = Goertzel::new( 852., fs, 205 ).expect("goertzel init failed");
+    let p_hit = hit.process( &x )[0].norm_sqr();
+    let p_miss = miss.process( &x )[0].norm_sqr();
+    assert!( nearly_equal( &p_hit.sqrt(), &( 205. / 2. ), &1. ) );
+    assert!( p_hit > 100. * p_miss );
+    assert!( Goertzel::<f32>::new( 100., 0., 10 ).is_err() );
+}
+
+#[test]
+fn test_sliding_dft() {
+    let x = test_signal::<f64>( 300 );
+    let n = 32;
+    let bins = [ 0, 3, 16, 31 ];
+    let mut sdft = SlidingDft::new( n, &bins ).expect("sliding dft init failed");
+    for (i, v) in x.iter().enumerate() {
+        let values = sdft.push( *v ).to_vec();
+        if i + 1 >= n {
+            let window = &x[ i + 1 - n ..= i ];
+            for (b, value) in bins.iter().zip( values.iter() ) {
+                let expected = dtft( window, *b as f64, n as f64 );
+                assert!( nearly_equal( &value.re, &expected.re, &1e-9 ) &&
+                         nearly_equal( &value.im, &expected.im, &1e-9 ) );
+            }
+        }
+    }
+    assert!( SlidingDft::<f64>::new( 8, &[ 8 ] ).is_err() );
+}
@@ -0,0 +1,97 @@
+///Analytic signal through the fft and instantaneous amplitude, phase and frequency
+extern crate num;
+
+use self::num::complex;
+
+use fft;
+use fft::FftFloat;
+use utility;
+
+#[cfg(test)]
+use test_common::nearly_equal;
+
+///x + i*H{x}, by zeroing negative frequencies and doubling positive ones
+pub fn hilbert<T: FftFloat>( x: &[T] ) -> Vec<complex::Complex<T>> {
+    let n = x.len();
+    if n == 0 {
+        return vec![]
+    }
+    let two = T::one() + T::one();
+    let spectrum = fft::fft_dif( x ).iter().enumerate().map( |(k, v)| {
+        if k == 0 || 2 * k == n {
+            *v
+        } else if 2 * k < n {
+            v * two
+        } else {
+            v * T::zero()
+        }
+    }).collect::<Vec<_>>();
+    fft::ifft_complex( &spectrum[..] )
+}
+
+///instantaneous amplitude |x + i*H{x}|
+pub fn envelope<T: FftFloat>( x: &[T] ) -> Vec<T> {
+    hilbert( x ).iter().map( |v| v.norm() ).collect()
+}
+
+///unwrapped instantaneous phase in radians
+pub fn instantaneous_phase<T: FftFloat>( x: &[T] ) -> Vec<T> {
+    let phase = hilbert( x ).iter().map( |v| v.arg() ).collect::<Vec<_>>();
+    utility::unwrap_phase( &phase[..] )
+}
+
+///instantaneous frequency in Hz from the phase difference of consecutive samples, n-1 values
+pub fn instantaneous_frequency<T: FftFloat>( x: &[T], fs: T ) -> Vec<T> {
+    let phase = instantaneous_phase( x );
+    let scale = fs / ( T::PI() + T::PI() );
+    phase.windows( 2 ).map( |w| ( w[1] - w[0] ) * scale ).collect()
+}
+
+#[test]
+fn test_hilbert_cosine() {
+    //a bin-centered cosine becomes a complex exponential
+    use std::f64::consts::PI;
+    for &n in [ 64, 63 ].iter() {
+        let x = (0..n).map( |i| ( 2. * PI * 5. * i as f64 / n as f64 ).cos() ).collect::<Vec<_>>();
+        let a = hilbert( &x );
+        assert_eq!( a.len(), n );
+        for (i, v) in a.iter().enumerate() {
+            let angle = 2. * PI * 5. * i as f64 / n as f64;
+            assert!( nearly_equal( &v.re, &angle.cos(), &1e-9 ) && nearly_equal( &v.im, &angle.sin(), &1e-9 ) );
+        }
+    }
+    assert!( hilbert::<f32>( &[] ).is_empty() );
+}
+
+#[test]
+fn test_envelope_demodulation() {
+    //amplitude modulated carrier, the envelope recovers the modulation
+    use std::f64::consts::PI;
+    let n = 1024;
+    let fs = 1024.;
+    let modulation = |t: f64| 1. + 0.5 * ( 2. * PI * 4. * t ).cos();
+    let x = (0..n).map( |i| {
+        let t = i as f64 / fs;
+        modulation( t ) * ( 2. * PI * 128. * t ).cos()
+    }).collect::<Vec<_>>();
+    let env = envelope( &x );
+    for (i, e) in env.iter().enumerate() {
+        assert!( nearly_equal( e, &modulation( i as f64 / fs ), &1e-9 ) );
+    }
+}
+
+#[test]
+fn test_instantaneous_frequency() {
+    use std::f64::consts::PI;
+    let n = 512;
+    let fs = 8000.;
+    let f0 = 1000.;
+    let x = (0..n).map( |i| ( 2. * PI * f0 * i as f64 / fs ).sin() ).collect::<Vec<_>>();
+    let phase = instantaneous_phase( &x );
+    //sin is cos delayed by a quarter turn
+    assert!( nearly_equal( &phase[0], &( -PI / 2. ), &1e-9 ) );
+    assert!( nearly_equal( &phase[ n - 1 ], &( -PI / 2. + 2. * PI * f0 * ( n - 1 ) as f64 / fs ), &1e-6 ) );
+    let f = instantaneous_frequency( &x, fs );
+    assert_eq!( f.len(), n - 1 );
+    assert!( f.iter().all( |v| nearly_equal( v, &f0, &1e-6 ) ) );
+}
@@ -0,0 +1,121 @@
+///Lomb-Scargle periodogram of unevenly sampled series, direct and through the type 1 nufft
+extern crate num;
+
+use self::num::complex;
+
+use error;
+use fft::{ Direction, FftFloat };
+use nufft;
+use nufft::NufftOptions;
+
+#[cfg(test)]
+use test_common::{ nearly_equal, uniform };
+
+///mean removed samples and their sum of squares
+fn centered<T: FftFloat>( t: &[T], y: &[T] ) -> Result< ( Vec<T>, T ), error::Error > {
+    if t.len() != y.len() {
+        return Err( error::Error::Dimension )
+    }
+    if y.len() < 2 {
+        return Err( error::Error::DataInsufficient )
+    }
+    let mean = y.iter().fold( T::zero(), |acc, v| acc + *v ) / T::from( y.len() ).unwrap();
+    let yc = y.iter().map( |v| *v - mean ).collect::<Vec<_>>();
+    let yy = yc.iter().fold( T::zero(), |acc, v| acc + *v * *v );
+    if yy <= T::zero() {
+        return Err( error::Error::DataInvalid )
+    }
+    Ok( ( yc, yy ) )
+}
+
+///normalized power at angular frequency w from the sums over n samples
+///c2 + i*s2 = sum e^(2iwt) and ch + i*sh = sum y*e^(iwt)
+fn power<T: FftFloat>( n: usize, yy: T, two: complex::Complex<T>, h: complex::Complex<T> ) -> T {
+    let half = T::from( 0.5 ).unwrap();
+    let n = T::from( n ).unwrap();
+    //tau makes the sine and cosine terms orthogonal, tan(2*w*tau) = s2/c2
+    let ( sin, cos ) = ( two.im.atan2( two.re ) * half ).sin_cos();
+    let r = two.norm();
+    let ( cc, ss ) = ( ( n + r ) * half, ( n - r ) * half );
+    let yc = h.re * cos + h.im * sin;
+    let ys = h.im * cos - h.re * sin;
+    //every sample at a node of the sine leaves only the cosine term
+    let eps = T::epsilon() * n;
+    let pc = if cc > eps { yc * yc / cc } else { T::zero() };
+    let ps = if ss > eps { ys * ys / ss } else { T::zero() };
+    ( pc + ps ) / yy
+}
+
+///power at each frequency in Hz of samples y taken at times t in seconds.
+///normalized by the variance of y so a pure sinusoid gives 1 at its frequency and the range is 0..1
+pub fn lomb_scargle<T: FftFloat>( t: &[T], y: &[T], freqs: &[T] ) -> Result< Vec<T>, error::Error > {
+    let ( yc, yy ) = centered( t, y )?;
+    let two_pi = T::PI() + T::PI();
+    Ok( freqs.iter().map( |f| {
+        let w = two_pi * *f;
+        let zero = complex::Complex::new( T::zero(), T::zero() );
+        let ( two, h ) = t.iter().zip( yc.iter() ).fold( ( zero, zero ), |( two, h ), (tj, yj)| {
+            let ( s, c ) = ( w * *tj ).sin_cos();
+            ( two + complex::Complex::new( c * c - s * s, ( s + s ) * c ), h + complex::Complex::new( c * *yj, s * *yj ) )
+        });
+        power( t.len(), yy, two, h )
+    }).collect() )
+}
+
+///frequencies k*df for k = 1..=m, the grid lomb_scargle_fast evaluates
+pub fn lomb_scargle_freqs<T: FftFloat>( df: T, m: usize ) -> Vec<T> {
+    (1..=m).map( |k| T::from( k ).unwrap() * df ).collect()
+}
+
+///lomb_scargle over lomb_scargle_freqs( df, m ), with the trigonometric sums from two type 1 nuffts
+///in O( n + m log m ) rather than O( n*m )
+pub fn lomb_scargle_fast<T: FftFloat>( t: &[T], y: &[T], df: T, m: usize, options: &NufftOptions ) -> Result< Vec<T>, error::Error > {
+    let ( yc, yy ) = centered( t, y )?;
+    let two_pi = T::PI() + T::PI();
+    //w_k*t = k * 2pi*df*t, reduced here while still exact in T
+    let x = t.iter().map( |tj| two_pi * ( df * *tj ).fract() ).collect::<Vec<_>>();
+    let ones = vec![ complex::Complex::new( T::one(), T::zero() ); t.len() ];
+    let yc = yc.iter().map( |v| complex::Complex::new( *v, T::zero() ) ).collect::<Vec<_>>();
+    //modes -(2m+1)..=2m cover 2k for k <= m, -(m+1)..=m cover k
+    let twos = nufft::nufft1( &x[..], &ones[..], 4 * m + 2, Direction::Inverse, options )?;
+    let hs = nufft::nufft1( &x[..], &yc[..], 2 * m + 2, Direction::Inverse, options )?;
+    Ok( (1..=m).map( |k| power( t.len(), yy, twos[ 2 * k + 2 * m + 1 ], hs[ k + m + 1 ] ) ).collect() )
+}
+
+#[cfg(test)]
+fn irregular_times( n: usize, span: f64, seed: u64 ) -> Vec<f64> {
+    let mut t = uniform( n, seed ).iter().map( |u| span * u ).collect::<Vec<_>>();
+    t.sort_by( |a, b| a.partial_cmp( b ).unwrap() );
+    t
+}
+
+#[test]
+fn test_lomb_scargle_sinusoid() {
+    let t = irregular_times( 300, 100., 7 );
+    let y = t.iter().map( |tj| ( 2. * ::std::f64::consts::PI * 0.37 * tj + 0.4 ).sin() + 2. ).collect::<Vec<_>>();
+    let freqs = lomb_scargle_freqs( 0.001, 1000 );
+    let p = lomb_scargle( &t[..], &y[..], &freqs[..] ).unwrap();
+    let best = (0..p.len()).max_by( |a, b| p[*a].partial_cmp( &p[*b] ).unwrap() ).unwrap();
+    assert!( nearly_equal( &freqs[best], &0.37, &0.0015 ) );
+    assert!( p[best] <= 1. + 1e-9 );
+    //only the sample mean is removed, not a fitted offset, so even the true frequency falls a little short of 1
+    let exact = lomb_scargle( &t[..], &y[..], &[ 0.37 ] ).unwrap();
+    assert!( exact[0] > 0.98 );
+    assert!( p.iter().all( |v| *v >= 0. ) );
+    assert!( p[100] < 0.1 );
+
+    assert!( lomb_scargle( &t[..], &y[..10], &freqs[..] ).is_err() );
+    assert!( lomb_scargle( &t[..], &vec![ 1.; 300 ][..], &freqs[..] ).is_err() );
+    assert!( lomb_scargle( &t[..1], &y[..1], &freqs[..] ).is_err() );
+}
+
+#[test]
+fn test_lomb_scargle_fast_matches_direct() {
+    let t = irregular_times( 500, 40., 11 );
+    let y = t.iter().map( |tj| ( 2. * ::std::f64::consts::PI * 1.3 * tj ).cos() + 0.5 * ( 2. * ::std::f64::consts::PI * 3.1 * tj ).sin() )
+        .collect::<Vec<_>>();
+    let ( df, m ) = ( 0.01, 600 );
+    let direct = lomb_scargle( &t[..], &y[..], &lomb_scargle_freqs( df, m )[..] ).unwrap();
+    let fast = lomb_scargle_fast( &t[..], &y[..], df, m, &NufftOptions::default() ).unwrap();
+    direct.iter().zip( fast.iter() ).for_each( |v| assert!( nearly_equal( v.0, v.1, &1e-6 ) ) );
+}
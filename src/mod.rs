@@ -1,10 +1,27 @@
 pub mod kalman;
 pub mod kalman_fuse;
 pub mod utility;
+pub mod dct;
 pub mod distribution;
+pub mod cepstrum;
+pub mod convolve;
+pub mod correlate;
+pub mod czt;
 pub mod error;
 pub mod fft;
+pub mod fft_parallel;
+pub mod fft_simd;
+pub mod goertzel;
+pub mod hilbert;
+pub mod lombscargle;
+pub mod ntt;
+pub mod nufft;
+pub mod peaks;
+pub mod psd;
 pub mod pursuit;
+pub mod spectrum;
+pub mod stft;
+pub mod window;
     
 #[macro_use]
 extern crate ndarray;
@@ -0,0 +1,263 @@
+///Number-theoretic transform over NTT-friendly prime fields for exact integer convolution
+use error;
+use fft;
+
+#[cfg(test)]
+use test_common::lcg;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Ntt {
+    modulus: u64,
+    primitive_root: u64,
+    ///largest power of 2 dividing modulus-1, bounds the transform length
+    max_log2: u32,
+}
+
+///998244353 = 119*2^23+1
+pub const NTT_998244353 : Ntt = Ntt { modulus: 998244353, primitive_root: 3, max_log2: 23 };
+///167772161 = 5*2^25+1
+pub const NTT_167772161 : Ntt = Ntt { modulus: 167772161, primitive_root: 3, max_log2: 25 };
+///469762049 = 7*2^26+1
+pub const NTT_469762049 : Ntt = Ntt { modulus: 469762049, primitive_root: 3, max_log2: 26 };
+
+pub fn mul_mod( a: u64, b: u64, m: u64 ) -> u64 {
+    ( a as u128 * b as u128 % m as u128 ) as u64
+}
+
+pub fn pow_mod( mut base: u64, mut exp: u64, m: u64 ) -> u64 {
+    let mut ret = 1 % m;
+    base %= m;
+    while exp > 0 {
+        if exp & 1 == 1 {
+            ret = mul_mod( ret, base, m );
+        }
+        base = mul_mod( base, base, m );
+        exp >>= 1;
+    }
+    ret
+}
+
+///inverse by Fermat's little theorem, m must be prime
+fn inv_mod( a: u64, m: u64 ) -> u64 {
+    pow_mod( a, m - 2, m )
+}
+
+///distinct prime factors by trial division, cheap for the moduli below 2^32 a field takes
+fn prime_factors( mut n: u64 ) -> Vec<u64> {
+    let mut ret = vec![];
+    let mut d = 2;
+    while d * d <= n {
+        if n % d == 0 {
+            ret.push( d );
+            while n % d == 0 {
+                n /= d;
+            }
+        }
+        d += 1;
+    }
+    if n > 1 {
+        ret.push( n );
+    }
+    ret
+}
+
+impl Ntt {
+
+    ///modulus must be a prime of the form c*2^k+1 with primitive_root generating its multiplicative group,
+    ///errors with DataInvalid otherwise
+    pub fn new( modulus: u64, primitive_root: u64 ) -> Result< Ntt, error::Error > {
+        if modulus < 3 || modulus > u32::MAX as u64 || prime_factors( modulus ) != vec![ modulus ] {
+            return Err( error::Error::DataInvalid )
+        }
+        //a generator has order exactly p-1, so no root^((p-1)/q) for a prime q | p-1 can be 1
+        let order = modulus - 1;
+        if primitive_root % modulus == 0 ||
+            prime_factors( order ).iter().any( |q| pow_mod( primitive_root, order / q, modulus ) == 1 ) {
+            return Err( error::Error::DataInvalid )
+        }
+        Ok( Ntt {
+            modulus,
+            primitive_root,
+            max_log2: ( modulus - 1 ).trailing_zeros(),
+        })
+    }
+
+    pub fn modulus( &self ) -> u64 {
+        self.modulus
+    }
+
+    ///largest supported transform length
+    pub fn max_len( &self ) -> usize {
+        1 << self.max_log2
+    }
+
+    ///in place transform of a power of 2 length, inverse includes the 1/n scaling
+    pub fn transform( &self, arr: &mut [u64], inverse: bool ) -> Result< (), error::Error > {
+        let n = arr.len();
+        if n == 0 || n & ( n - 1 ) != 0 || n > self.max_len() {
+            return Err( error::Error::Dimension )
+        }
+        let p = self.modulus;
+        let levels = n.trailing_zeros();
+
+        //re-arrange bottom level of the tree
+        for i in 0..n {
+            let rev_num = fft::reverse_bits( i as u32, levels ) as usize;
+            if i < rev_num {
+                arr.swap( i, rev_num );
+            }
+        }
+        for s in 0..levels { //for each tree level
+            let m = 1 << ( s + 1 ); //2^(s+1) = decimation group size
+            //primitive m'th root of unity in the field
+            let w_m = pow_mod( self.primitive_root, ( p - 1 ) / m as u64, p );
+            let w_base = if inverse { inv_mod( w_m, p ) } else { w_m };
+            let mut k = 0;
+            while k < n { //for each butterfly group
+                let mut w = 1; //twiddle factor
+                for j in 0..m/2 { //for each butterfly pair in current group
+                    let odd = mul_mod( w, arr[ k + j + m/2 ], p );
+                    let even = arr[ k + j ];
+                    arr[ k + j ] = ( even + odd ) % p;
+                    arr[ k + j + m/2 ] = ( even + p - odd ) % p;
+                    w = mul_mod( w, w_base, p );
+                }
+                k += m;
+            }
+        }
+        if inverse {
+            let n_inv = inv_mod( n as u64 % p, p );
+            for x in arr.iter_mut() {
+                *x = mul_mod( *x, n_inv, p );
+            }
+        }
+        Ok( () )
+    }
+
+    ///linear convolution of residues mod the field prime, length a.len()+b.len()-1
+    pub fn convolve( &self, a: &[u64], b: &[u64] ) -> Result< Vec<u64>, error::Error > {
+        if a.is_empty() || b.is_empty() {
+            return Ok( vec![] )
+        }
+        let len = a.len() + b.len() - 1;
+        let mut n = 1;
+        while n < len {
+            n <<= 1;
+        }
+        let p = self.modulus;
+        let mut fa = a.iter().map( |x| x % p ).collect::<Vec<_>>();
+        fa.resize( n, 0 );
+        let mut fb = b.iter().map( |x| x % p ).collect::<Vec<_>>();
+        fb.resize( n, 0 );
+        self.transform( &mut fa[..], false )?;
+        self.transform( &mut fb[..], false )?;
+        for (x, y) in fa.iter_mut().zip( fb.iter() ) {
+            *x = mul_mod( *x, *y, p );
+        }
+        self.transform( &mut fa[..], true )?;
+        fa.truncate( len );
+        Ok( fa )
+    }
+}
+
+///exact linear convolution of signed integers through three prime fields and CRT recombination.
+///errors with DataInvalid when max|a| * max|b| * min( |a|, |b| ) could exceed p1*p2*p3/2 ~ 3.9e25
+pub fn convolve_exact( a: &[i64], b: &[i64] ) -> Result< Vec<i128>, error::Error > {
+    let fields = [ NTT_998244353, NTT_167772161, NTT_469762049 ];
+    let total = fields.iter().fold( 1u128, |acc, f| acc * f.modulus as u128 );
+    let max_abs = |v: &[i64]| v.iter().fold( 0u128, |acc, x| acc.max( ( *x as i128 ).unsigned_abs() ) );
+    let bound = max_abs( a ).checked_mul( max_abs( b ) ).and_then( |x| x.checked_mul( a.len().min( b.len() ) as u128 ) );
+    match bound {
+        Some( x ) if x <= total / 2 => {},
+        _ => return Err( error::Error::DataInvalid ),
+    }
+    let mut residues = vec![];
+    for f in fields.iter() {
+        let p = f.modulus as i64;
+        let ra = a.iter().map( |x| x.rem_euclid( p ) as u64 ).collect::<Vec<_>>();
+        let rb = b.iter().map( |x| x.rem_euclid( p ) as u64 ).collect::<Vec<_>>();
+        residues.push( f.convolve( &ra[..], &rb[..] )? );
+    }
+    let ( p1, p2, p3 ) = ( fields[0].modulus, fields[1].modulus, fields[2].modulus );
+    let p1_inv = inv_mod( p1 % p2, p2 );
+    let p12 = p1 as u128 * p2 as u128;
+    let p12_inv = inv_mod( ( p12 % p3 as u128 ) as u64, p3 );
+    //Garner's mixed radix recombination
+    Ok( (0..residues[0].len()).map( |i| {
+        let ( r1, r2, r3 ) = ( residues[0][i], residues[1][i], residues[2][i] );
+        let t = mul_mod( ( r2 + p2 - r1 % p2 ) % p2, p1_inv, p2 );
+        let x12 = r1 as u128 + p1 as u128 * t as u128;
+        let t = mul_mod( ( r3 + p3 - ( x12 % p3 as u128 ) as u64 ) % p3, p12_inv, p3 );
+        let x = x12 + p12 * t as u128;
+        if x > total / 2 {
+            x as i128 - total as i128
+        } else {
+            x as i128
+        }
+    }).collect() )
+}
+
+///exact product of integer polynomials, coefficients ordered from the constant term up
+pub fn poly_mul( a: &[i64], b: &[i64] ) -> Result< Vec<i128>, error::Error > {
+    convolve_exact( a, b )
+}
+
+#[cfg(test)]
+fn direct( a: &[i64], b: &[i64] ) -> Vec<i128> {
+    let mut ret = vec![ 0i128; a.len() + b.len() - 1 ];
+    for (i, x) in a.iter().enumerate() {
+        for (j, y) in b.iter().enumerate() {
+            ret[ i + j ] += *x as i128 * *y as i128;
+        }
+    }
+    ret
+}
+
+#[cfg(test)]
+fn test_values( n: usize, seed: u64, range: i64 ) -> Vec<i64> {
+    lcg( seed ).take( n ).map( |s| ( ( s >> 20 ) % ( 2 * range as u64 + 1 ) ) as i64 - range ).collect()
+}
+
+#[test]
+fn test_ntt_round_trip() {
+    for f in [ NTT_998244353, NTT_167772161, NTT_469762049 ].iter() {
+        let original = test_values( 64, 3, 1000 ).iter().map( |x| x.rem_euclid( f.modulus() as i64 ) as u64 ).collect::<Vec<_>>();
+        let mut arr = original.clone();
+        f.transform( &mut arr[..], false ).expect("ntt failed");
+        assert!( arr != original );
+        f.transform( &mut arr[..], true ).expect("intt failed");
+        assert_eq!( arr, original );
+    }
+    let mut arr = [ 1u64; 12 ];
+    assert!( NTT_998244353.transform( &mut arr[..], false ).is_err() );
+}
+
+#[test]
+fn test_ntt_convolve_mod() {
+    let ntt = Ntt::new( 998244353, 3 ).expect("ntt init failed");
+    assert_eq!( ntt.max_len(), 1 << 23 );
+    assert_eq!( ntt.convolve( &[ 1, 2, 3 ], &[ 4, 5 ] ).unwrap(), vec![ 4, 13, 22, 15 ] );
+    //wraps modulo the prime
+    let p = ntt.modulus();
+    assert_eq!( ntt.convolve( &[ p - 1 ], &[ p - 1 ] ).unwrap(), vec![ 1 ] );
+    assert!( Ntt::new( 4, 0 ).is_err() );
+    //composite modulus, and 2 is a quadratic residue mod 998244353 so it generates only half the group
+    assert!( Ntt::new( 15, 2 ).is_err() );
+    assert!( Ntt::new( 998244353, 2 ).is_err() );
+    assert!( Ntt::new( 469762049, 3 ).is_ok() );
+}
+
+#[test]
+fn test_convolve_exact() {
+    assert_eq!( poly_mul( &[ 1, 1 ], &[ -1, 1 ] ).unwrap(), vec![ -1, 0, 1 ] );
+    //products and sums far beyond a single 30 bit field and beyond f64 integer precision
+    let a = test_values( 1000, 1, 1_000_000_000 );
+    let b = test_values( 777, 2, 1_000_000_000 );
+    let out = convolve_exact( &a, &b ).unwrap();
+    assert_eq!( out, direct( &a, &b ) );
+    assert!( out.iter().any( |x| x.abs() > 1 << 60 ) );
+    //outputs beyond half the combined modulus would wrap
+    let big = 1i64 << 62;
+    assert!( convolve_exact( &[ big, big ], &[ big, -big ] ).is_err() );
+    assert!( convolve_exact( &[ i64::MIN ], &[ 1 ] ).is_ok() );
+}
@@ -0,0 +1,254 @@
+///Non-uniform fft of types 1 and 2 by gridding onto an oversampled uniform fft
+extern crate num;
+
+use self::num::complex;
+
+use error;
+use fft::{ Direction, FftFloat, FftPlan };
+use window;
+
+#[cfg(test)]
+use test_common::{ nearly_equal, uniform };
+
+use std::f64::consts::PI;
+
+///fine grid length over the number of modes
+const OVERSAMPLING : f64 = 2.;
+
+///kernel points are spread onto the fine grid with
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Gridding {
+    ///truncated Gaussian of Greengard and Lee
+    Gaussian,
+    ///Kaiser-Bessel with the shape of Beatty et al., narrower than Gaussian for the same accuracy
+    KaiserBessel,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct NufftOptions {
+    pub gridding: Gridding,
+    ///target relative accuracy, sets the kernel width
+    pub tolerance: f64,
+}
+
+impl Default for NufftOptions {
+    fn default() -> Self {
+        Self {
+            gridding: Gridding::KaiserBessel,
+            tolerance: 1e-9,
+        }
+    }
+}
+
+///spreading kernel in units of fine grid points, supported on |u| < width/2
+#[derive(Debug, Clone, Copy)]
+struct Spreader {
+    gridding: Gridding,
+    width: usize,
+    ///Gaussian variance parameter tau or Kaiser-Bessel beta
+    shape: f64,
+}
+
+impl Spreader {
+
+    ///errors with DataInvalid unless the tolerance is positive
+    fn new( options: &NufftOptions ) -> Result< Spreader, error::Error > {
+        if options.tolerance.is_nan() || options.tolerance <= 0. {
+            return Err( error::Error::DataInvalid )
+        }
+        let digits = -options.tolerance.clamp( 1e-15, 0.1 ).ln();
+        Ok( match options.gridding {
+            Gridding::Gaussian => {
+                //error ~ exp( -msp*pi*(s-1)/(s-1/2) ) with msp points either side
+                let msp = ( digits * ( OVERSAMPLING - 0.5 ) / ( PI * ( OVERSAMPLING - 1. ) ) ).ceil().max( 2. );
+                Spreader {
+                    gridding: Gridding::Gaussian,
+                    width: 2 * msp as usize,
+                    shape: msp * OVERSAMPLING / ( 4. * PI * ( OVERSAMPLING - 0.5 ) ),
+                }
+            },
+            Gridding::KaiserBessel => {
+                //error ~ exp( -pi*w*sqrt(1-1/s) )
+                let width = ( digits / ( PI * ( 1. - 1. / OVERSAMPLING ).sqrt() ) ).ceil().max( 3. ) + 1.;
+                let beta = PI * ( ( width / OVERSAMPLING * ( OVERSAMPLING - 0.5 ) ).powi( 2 ) - 0.8 ).sqrt();
+                Spreader {
+                    gridding: Gridding::KaiserBessel,
+                    width: width as usize,
+                    shape: beta,
+                }
+            },
+        })
+    }
+
+    fn eval( &self, u: f64 ) -> f64 {
+        let half = self.width as f64 / 2.;
+        if u.abs() >= half {
+            return 0.
+        }
+        match self.gridding {
+            Gridding::Gaussian => ( -u * u / ( 4. * self.shape ) ).exp(),
+            Gridding::KaiserBessel => window::bessel_i0( self.shape * ( 1. - ( u / half ).powi( 2 ) ).sqrt() ),
+        }
+    }
+
+    ///integral of eval(u) * e^(i*w*u) over u, real as the kernel is even
+    fn transform( &self, w: f64 ) -> f64 {
+        match self.gridding {
+            Gridding::Gaussian => 2. * ( PI * self.shape ).sqrt() * ( -w * w * self.shape ).exp(),
+            Gridding::KaiserBessel => {
+                let half = self.width as f64 / 2.;
+                let d = self.shape * self.shape - ( half * w ).powi( 2 );
+                if d > 0. {
+                    2. * half * d.sqrt().sinh() / d.sqrt()
+                } else if d < 0. {
+                    2. * half * ( -d ).sqrt().sin() / ( -d ).sqrt()
+                } else {
+                    2. * half
+                }
+            },
+        }
+    }
+
+    ///fills out with the kernel weights of a point at grid position u and returns the first grid index they apply to
+    fn weights( &self, u: f64, out: &mut [f64] ) -> isize {
+        let start = ( u - self.width as f64 / 2. ).ceil() as isize;
+        for (i, w) in out.iter_mut().enumerate() {
+            *w = self.eval( ( start + i as isize ) as f64 - u );
+        }
+        start
+    }
+}
+
+///fine grid length, even and at least twice the kernel width
+fn fine_len( m: usize, width: usize ) -> usize {
+    let n = ( OVERSAMPLING * m as f64 ).ceil() as usize;
+    let n = n.max( 2 * width );
+    n + n % 2
+}
+
+///mode k of m modes sits at index k + m/2 of the centered order
+fn mode( index: usize, m: usize ) -> isize {
+    index as isize - ( m / 2 ) as isize
+}
+
+///type 1, non-uniform points to uniform modes: f[k] = sum_j c[j] * e^(sign*i*k*x[j]) for k = -m/2 .. m - m/2 - 1,
+///returned in that centered order. Forward is sign -1 and Inverse +1, x is taken modulo 2pi
+pub fn nufft1<T: FftFloat>( x: &[T], c: &[complex::Complex<T>], m: usize, direction: Direction, options: &NufftOptions ) -> Result< Vec<complex::Complex<T>>, error::Error > {
+    if x.len() != c.len() {
+        return Err( error::Error::Dimension )
+    }
+    let spreader = Spreader::new( options )?;
+    let n = fine_len( m, spreader.width );
+    let h = 2. * PI / n as f64;
+
+    //spread every point onto the periodic fine grid
+    let mut grid = vec![ complex::Complex::new( T::zero(), T::zero() ); n ];
+    let mut weights = vec![ 0f64; spreader.width ];
+    for (xj, cj) in x.iter().zip( c.iter() ) {
+        let u = xj.to_f64().unwrap().rem_euclid( 2. * PI ) / h;
+        let start = spreader.weights( u, &mut weights[..] );
+        for (i, w) in weights.iter().enumerate() {
+            let l = ( start + i as isize ).rem_euclid( n as isize ) as usize;
+            grid[l] += cj * T::from( *w ).unwrap();
+        }
+    }
+
+    FftPlan::new( n, direction ).run( &mut grid[..] );
+
+    //divide out the kernel's transform, modes below zero wrap to the top of the grid
+    Ok( (0..m).map( |i| {
+        let k = mode( i, m );
+        let correction = T::from( spreader.transform( k as f64 * h ) ).unwrap();
+        grid[ k.rem_euclid( n as isize ) as usize ] / correction
+    }).collect() )
+}
+
+///type 2, uniform modes to non-uniform points: c[j] = sum_k f[k] * e^(sign*i*k*x[j]) with f in the
+///centered order nufft1 returns. Forward is sign -1 and Inverse +1, x is taken modulo 2pi
+pub fn nufft2<T: FftFloat>( x: &[T], f: &[complex::Complex<T>], direction: Direction, options: &NufftOptions ) -> Result< Vec<complex::Complex<T>>, error::Error > {
+    let m = f.len();
+    let spreader = Spreader::new( options )?;
+    let n = fine_len( m, spreader.width );
+    let h = 2. * PI / n as f64;
+
+    //pre-divide by the kernel's transform so interpolating with it restores f
+    let mut grid = vec![ complex::Complex::new( T::zero(), T::zero() ); n ];
+    for (i, fk) in f.iter().enumerate() {
+        let k = mode( i, m );
+        let correction = T::from( spreader.transform( k as f64 * h ) ).unwrap();
+        grid[ k.rem_euclid( n as isize ) as usize ] = fk / correction;
+    }
+
+    FftPlan::new( n, direction ).run( &mut grid[..] );
+
+    let mut weights = vec![ 0f64; spreader.width ];
+    Ok( x.iter().map( |xj| {
+        let u = xj.to_f64().unwrap().rem_euclid( 2. * PI ) / h;
+        let start = spreader.weights( u, &mut weights[..] );
+        weights.iter().enumerate().fold( complex::Complex::new( T::zero(), T::zero() ), |acc, (i, w)| {
+            let l = ( start + i as isize ).rem_euclid( n as isize ) as usize;
+            acc + grid[l] * T::from( *w ).unwrap()
+        })
+    }).collect() )
+}
+
+#[cfg(test)]
+fn direct<T: FftFloat>( x: &[T], c: &[complex::Complex<T>], modes: &[isize], sign: T ) -> Vec<complex::Complex<T>> {
+    modes.iter().map( |k| {
+        x.iter().zip( c.iter() ).fold( complex::Complex::new( T::zero(), T::zero() ), |acc, (xj, cj)| {
+            acc + cj * complex::Complex::from_polar( &T::one(), &( sign * T::from( *k ).unwrap() * *xj ) )
+        })
+    }).collect()
+}
+
+#[cfg(test)]
+fn max_error( a: &[complex::Complex<f64>], b: &[complex::Complex<f64>] ) -> f64 {
+    let scale = b.iter().fold( 0f64, |acc, v| acc.max( v.norm() ) );
+    a.iter().zip( b.iter() ).fold( 0f64, |acc, (p, q)| acc.max( ( p - q ).norm() ) ) / scale
+}
+
+#[test]
+fn test_nufft_matches_direct() {
+    let x = uniform( 200, 1 ).iter().map( |u| ( u - 0.5 ) * 4. * PI ).collect::<Vec<_>>();
+    let c = uniform( 200, 2 ).iter().zip( uniform( 200, 3 ).iter() ).map( |(a, b)| complex::Complex::new( a - 0.5, b - 0.5 ) ).collect::<Vec<_>>();
+    for &gridding in [ Gridding::Gaussian, Gridding::KaiserBessel ].iter() {
+        for &tolerance in [ 1e-4, 1e-9 ].iter() {
+            let options = NufftOptions { gridding, tolerance };
+            for &m in [ 1, 16, 51 ].iter() {
+                let modes = (0..m).map( |i| mode( i, m ) ).collect::<Vec<_>>();
+                for &( direction, sign ) in [ ( Direction::Forward, -1. ), ( Direction::Inverse, 1. ) ].iter() {
+                    let f = nufft1( &x[..], &c[..], m, direction, &options ).unwrap();
+                    let expect = direct( &x[..], &c[..], &modes[..], sign );
+                    let err = max_error( &f[..], &expect[..] );
+                    assert!( err < tolerance * 10., "type 1 {:?} {} {} {}", gridding, tolerance, m, err );
+
+                    let fk = c[..m].to_vec();
+                    let got = nufft2( &x[..], &fk[..], direction, &options ).unwrap();
+                    let expect = x.iter().map( |xj| {
+                        modes.iter().zip( fk.iter() ).fold( complex::Complex::new( 0., 0. ), |acc, (k, v)| {
+                            acc + v * complex::Complex::from_polar( &1., &( sign * *k as f64 * xj ) )
+                        })
+                    }).collect::<Vec<_>>();
+                    let err = max_error( &got[..], &expect[..] );
+                    assert!( err < tolerance * 10., "type 2 {:?} {} {} {}", gridding, tolerance, m, err );
+                }
+            }
+        }
+    }
+    assert!( nufft1( &x[..], &c[..10], 8, Direction::Forward, &NufftOptions::default() ).is_err() );
+    let invalid = NufftOptions { tolerance: 0., ..NufftOptions::default() };
+    assert!( nufft1( &x[..], &c[..], 8, Direction::Forward, &invalid ).is_err() );
+    assert!( nufft2( &x[..], &c[..8], Direction::Forward, &invalid ).is_err() );
+}
+
+#[test]
+fn test_nufft_f32() {
+    let x = uniform( 64, 4 ).iter().map( |u| ( u * 2. * PI ) as f32 ).collect::<Vec<_>>();
+    let c = x.iter().map( |v| complex::Complex::new( v.cos(), 0f32 ) ).collect::<Vec<_>>();
+    let options = NufftOptions { tolerance: 1e-6, ..NufftOptions::default() };
+    let f = nufft1( &x[..], &c[..], 32, Direction::Forward, &options ).unwrap();
+    let modes = (0..32).map( |i| mode( i, 32 ) ).collect::<Vec<_>>();
+    let expect = direct( &x[..], &c[..], &modes[..], -1f32 );
+    f.iter().zip( expect.iter() )
+        .for_each( |v| assert!( nearly_equal( &v.0.re, &v.1.re, &1e-3 ) && nearly_equal( &v.0.im, &v.1.im, &1e-3 ) ) );
+}
@@ -0,0 +1,310 @@
+///Spectral peak picking with prominence and spacing filters and sub-bin frequency and amplitude refinement
+extern crate num;
+
+use self::num::complex;
+use std::cmp::Ordering;
+
+use error;
+use fft::{ FftFloat, Order };
+use spectrum::{ Sides, Spectrum };
+use window::{ Symmetry, Window };
+
+#[cfg(test)]
+use test_common::nearly_equal;
+
+///sub-bin estimator applied around each local maximum
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Interpolation {
+    ///bin centre frequency
+    None,
+    ///parabola through the 3 magnitudes around the maximum
+    Parabolic,
+    ///parabola through the log magnitudes, exact for Gaussian windows and close for Hann and Blackman
+    Gaussian,
+    ///Quinn's second estimator on the complex bins, for rectangular windows
+    Quinn,
+    ///Jacobsen's estimator on the complex bins, least biased for rectangular windows
+    Jacobsen,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct PeakOptions<T: FftFloat> {
+    ///peaks with a corrected amplitude below this, in dB of amplitude, are dropped
+    pub threshold_db: T,
+    ///peaks standing less than this above the higher of their surrounding minima, in dB, are dropped
+    pub min_prominence_db: T,
+    ///of two peaks closer than this in Hz the weaker one is dropped
+    pub min_distance_hz: T,
+    ///keeps only the strongest peaks
+    pub max_peaks: Option<usize>,
+    pub interpolation: Interpolation,
+    ///window the spectrum was computed with, for amplitude correction
+    pub window: Window,
+    pub symmetry: Symmetry,
+}
+
+impl<T: FftFloat> Default for PeakOptions<T> {
+    fn default() -> Self {
+        Self {
+            threshold_db: T::neg_infinity(),
+            min_prominence_db: T::zero(),
+            min_distance_hz: T::zero(),
+            max_peaks: None,
+            interpolation: Interpolation::Parabolic,
+            window: Window::Rectangular,
+            symmetry: Symmetry::Periodic,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Peak<T: FftFloat> {
+    ///index of the local maximum in the spectrum bins
+    pub bin: usize,
+    ///refined position relative to bin, in bins
+    pub offset: T,
+    ///refined frequency in Hz
+    pub frequency: T,
+    ///|X| at bin
+    pub magnitude: T,
+    ///amplitude of the underlying sinusoid corrected for window gain and scalloping loss.
+    ///one-sided spectra give real sinusoid amplitudes, two-sided ones complex exponential amplitudes
+    pub amplitude: T,
+    ///height above the higher of the minima between the peak and the nearest higher bins either side, in dB
+    pub prominence_db: T,
+}
+
+///local maxima of spectrum that pass the filters in options, in increasing bin order.
+///bins at the ends of one-sided or centered spectra have only one neighbour and are never peaks,
+///two-sided spectra in fft order wrap around
+pub fn find_peaks<T: FftFloat>( spectrum: &Spectrum<T>, options: &PeakOptions<T> ) -> Result< Vec<Peak<T>>, error::Error > {
+    let bins = spectrum.bins();
+    let len = bins.len();
+    if len < 3 {
+        return Err( error::Error::DataInsufficient )
+    }
+    let circular = spectrum.sides() == Sides::TwoSided && spectrum.order() == Order::Natural;
+    let neighbours = |i: usize| -> Option<( usize, usize )> {
+        if circular {
+            Some( ( ( i + len - 1 ) % len, ( i + 1 ) % len ) )
+        } else if i == 0 || i == len - 1 {
+            None
+        } else {
+            Some( ( i - 1, i + 1 ) )
+        }
+    };
+    let mags = spectrum.magnitude();
+    let db = mags.iter().map( |x| to_db( *x ) ).collect::<Vec<_>>();
+    let coeff = options.window.coefficients::<T>( spectrum.signal_len(), options.symmetry )?;
+    let one_sided = spectrum.sides() == Sides::OneSided;
+    let n = spectrum.signal_len();
+
+    let mut peaks = vec![];
+    for i in 0..len {
+        let ( l, r ) = match neighbours( i ) {
+            Some( x ) => x,
+            _ => continue,
+        };
+        //strict on the left so a plateau yields one peak at its first bin
+        if !( mags[i] > mags[l] && mags[i] >= mags[r] ) {
+            continue
+        }
+        let prominence_db = prominence( &db[..], i );
+        if prominence_db < options.min_prominence_db {
+            continue
+        }
+        let offset = refine( options.interpolation, [ bins[l], bins[i], bins[r] ], [ mags[l], mags[i], mags[r] ] );
+        //real sinusoids split their amplitude between the bin and its mirror, except at dc and nyquist
+        let sides = if one_sided && i != 0 && !( n % 2 == 0 && i == n / 2 ) { T::one() + T::one() } else { T::one() };
+        let amplitude = sides * mags[i] / window_response( &coeff[..], offset );
+        let frequency = spectrum.bin_freq( i ).unwrap() + offset * spectrum.resolution();
+        //non-finite bins or a zero window response leave nothing to rank
+        if !amplitude.is_finite() || !frequency.is_finite() || to_db( amplitude ) < options.threshold_db {
+            continue
+        }
+        peaks.push( Peak { bin: i, offset, frequency, magnitude: mags[i], amplitude, prominence_db } );
+    }
+
+    //strongest first so spacing and count limits keep the larger peaks
+    peaks.sort_by( |a, b| b.amplitude.partial_cmp( &a.amplitude ).unwrap_or( Ordering::Equal ) );
+    let mut kept : Vec<Peak<T>> = vec![];
+    for p in peaks {
+        if options.max_peaks.map_or( false, |m| kept.len() >= m ) {
+            break
+        }
+        if kept.iter().all( |q| ( q.frequency - p.frequency ).abs() >= options.min_distance_hz ) {
+            kept.push( p );
+        }
+    }
+    kept.sort_by_key( |p| p.bin );
+    Ok( kept )
+}
+
+fn to_db<T: FftFloat>( x: T ) -> T {
+    if x > T::zero() {
+        T::from( 20. ).unwrap() * x.log10()
+    } else {
+        T::neg_infinity()
+    }
+}
+
+///scans out from peak to the first higher bin or the end on each side, prominence is relative to the higher of the two minima
+fn prominence<T: FftFloat>( db: &[T], peak: usize ) -> T {
+    let h = db[peak];
+    let left_min = db[..peak].iter().rev().take_while( |x| **x <= h ).fold( h, |acc, x| acc.min( *x ) );
+    let right_min = db[ peak + 1 .. ].iter().take_while( |x| **x <= h ).fold( h, |acc, x| acc.min( *x ) );
+    h - left_min.max( right_min )
+}
+
+///offset in bins of the true maximum from the centre of 3 neighbouring bins
+fn refine<T: FftFloat>( interpolation: Interpolation, x: [ complex::Complex<T>; 3 ], mag: [ T; 3 ] ) -> T {
+    let half = T::from( 0.5 ).unwrap();
+    let parabola = |a: T, b: T, c: T| {
+        let denom = a - ( b + b ) + c;
+        if denom == T::zero() { T::zero() } else { half * ( a - c ) / denom }
+    };
+    let offset = match interpolation {
+        Interpolation::None => T::zero(),
+        Interpolation::Parabolic => parabola( mag[0], mag[1], mag[2] ),
+        Interpolation::Gaussian => {
+            if mag.iter().any( |m| *m <= T::zero() ) {
+                parabola( mag[0], mag[1], mag[2] )
+            } else {
+                parabola( mag[0].ln(), mag[1].ln(), mag[2].ln() )
+            }
+        },
+        Interpolation::Quinn => {
+            let norm = x[1].norm_sqr();
+            if norm == T::zero() {
+                return T::zero()
+            }
+            let ap = ( x[2] * x[1].conj() ).re / norm;
+            let am = ( x[0] * x[1].conj() ).re / norm;
+            let dp = -ap / ( T::one() - ap );
+            let dm = am / ( T::one() - am );
+            ( dp + dm ) * half + quinn_tau( dp * dp ) - quinn_tau( dm * dm )
+        },
+        Interpolation::Jacobsen => {
+            let denom = x[1] + x[1] - x[0] - x[2];
+            if denom.norm_sqr() == T::zero() { T::zero() } else { ( ( x[0] - x[2] ) / denom ).re }
+        },
+    };
+    //a maximum of 3 samples lies within half a bin of the centre
+    offset.max( -half ).min( half )
+}
+
+fn quinn_tau<T: FftFloat>( x: T ) -> T {
+    let c = T::from( ( 2f64 / 3. ).sqrt() ).unwrap();
+    let three = T::from( 3. ).unwrap();
+    let six = T::from( 6. ).unwrap();
+    T::from( 0.25 ).unwrap() * ( three * x * x + six * x + T::one() ).ln()
+        - T::from( 6f64.sqrt() / 24. ).unwrap() * ( ( x + T::one() - c ) / ( x + T::one() + c ) ).ln()
+}
+
+///|sum_k w[k] e^(-2pi*i*offset*k/n)|, the gain of the window for a tone offset bins from a bin centre
+fn window_response<T: FftFloat>( coeff: &[T], offset: T ) -> T {
+    let n = T::from( coeff.len() ).unwrap();
+    let step = -( T::PI() + T::PI() ) * offset / n;
+    coeff.iter().enumerate().fold( complex::Complex::new( T::zero(), T::zero() ), |acc, (k, w)| {
+        acc + complex::Complex::from_polar( w, &( step * T::from( k ).unwrap() ) )
+    }).norm()
+}
+
+#[cfg(test)]
+fn tones( n: usize, fs: f64, components: &[ ( f64, f64 ) ] ) -> Vec<f64> {
+    (0..n).map( |i| {
+        components.iter().map( |&( a, f )| a * ( 2. * ::std::f64::consts::PI * f * i as f64 / fs + 0.3 ).cos() ).sum()
+    }).collect()
+}
+
+#[test]
+fn test_peaks_sub_bin_windowed() {
+    //1 Hz bins, tones off bin centres. leakage of the strong tone bounds the rectangular window accuracy
+    let ( n, fs ) = ( 256, 256. );
+    let signal = tones( n, fs, &[ ( 1.0, 40.3 ), ( 0.25, 90.7 ) ] );
+    for &( window, interpolation, tol_f, tol_a ) in [ ( Window::Hann, Interpolation::Parabolic, 0.1, 0.02 ),
+                                                      ( Window::Hann, Interpolation::Gaussian, 0.02, 0.01 ),
+                                                      ( Window::Blackman, Interpolation::Gaussian, 0.02, 0.01 ),
+                                                      ( Window::Rectangular, Interpolation::Quinn, 0.03, 0.01 ),
+                                                      ( Window::Rectangular, Interpolation::Jacobsen, 0.03, 0.01 ) ].iter() {
+        let windowed = window.apply( &signal[..], Symmetry::Periodic ).unwrap();
+        let spectrum = Spectrum::rfft( &windowed[..], fs );
+        let options = PeakOptions { interpolation, window, threshold_db: -30., min_prominence_db: 6., ..PeakOptions::default() };
+        let peaks = find_peaks( &spectrum, &options ).unwrap();
+        assert_eq!( peaks.len(), 2, "{:?} {:?}", window, interpolation );
+        assert_eq!( peaks[0].bin, 40 );
+        assert!( nearly_equal( &peaks[0].frequency, &40.3, &tol_f ), "{:?} {:?} {:?}", window, interpolation, peaks[0] );
+        assert!( nearly_equal( &peaks[1].frequency, &90.7, &tol_f ), "{:?} {:?} {:?}", window, interpolation, peaks[1] );
+        assert!( nearly_equal( &peaks[0].amplitude, &1.0, &tol_a ), "{:?} {:?} {:?}", window, interpolation, peaks[0] );
+        assert!( nearly_equal( &peaks[1].amplitude, &0.25, &tol_a ), "{:?} {:?} {:?}", window, interpolation, peaks[1] );
+    }
+}
+
+#[test]
+fn test_peaks_filters() {
+    let ( n, fs ) = ( 256, 256. );
+    let signal = tones( n, fs, &[ ( 1.0, 40. ), ( 0.5, 44. ), ( 0.01, 100. ) ] );
+    let windowed = Window::Hann.apply( &signal[..], Symmetry::Periodic ).unwrap();
+    let spectrum = Spectrum::rfft( &windowed[..], fs );
+    //integer bin tones under Hann leave only rounding noise outside 3 bins, the threshold drops its ripples
+    let base = PeakOptions { window: Window::Hann, threshold_db: -100., ..PeakOptions::default() };
+    let peaks = find_peaks( &spectrum, &base ).unwrap();
+    assert_eq!( peaks.iter().map( |p| p.bin ).collect::<Vec<_>>(), vec![ 40, 44, 100 ] );
+    assert!( nearly_equal( &peaks[2].amplitude, &0.01, &1e-6 ) );
+
+    let peaks = find_peaks( &spectrum, &PeakOptions { threshold_db: -20., ..base } ).unwrap();
+    assert_eq!( peaks.iter().map( |p| p.bin ).collect::<Vec<_>>(), vec![ 40, 44 ] );
+
+    let peaks = find_peaks( &spectrum, &PeakOptions { min_distance_hz: 5., ..base } ).unwrap();
+    assert_eq!( peaks.iter().map( |p| p.bin ).collect::<Vec<_>>(), vec![ 40, 100 ] );
+
+    let peaks = find_peaks( &spectrum, &PeakOptions { max_peaks: Some( 1 ), ..base } ).unwrap();
+    assert_eq!( peaks.iter().map( |p| p.bin ).collect::<Vec<_>>(), vec![ 40 ] );
+
+    //a shallow ripple on a slope is a local maximum with little prominence
+    let mags = [ 1., 2., 4., 8., 7.5, 7.9, 6., 1., 0.5 ];
+    let bins = mags.iter().map( |m| complex::Complex::new( *m, 0. ) ).collect::<Vec<_>>();
+    let spectrum = Spectrum::from_rfft( bins, 16, 16. ).unwrap();
+    let options = PeakOptions { interpolation: Interpolation::None, ..PeakOptions::default() };
+    let peaks = find_peaks( &spectrum, &options ).unwrap();
+    assert_eq!( peaks.iter().map( |p| p.bin ).collect::<Vec<_>>(), vec![ 3, 5 ] );
+    assert!( nearly_equal( &peaks[1].prominence_db, &( 20. * ( 7.9f64 / 7.5 ).log10() ), &1e-9 ) );
+    assert!( nearly_equal( &peaks[0].prominence_db, &( 20. * 8f64.log10() ), &1e-9 ) );
+    let peaks = find_peaks( &spectrum, &PeakOptions { min_prominence_db: 1., ..options } ).unwrap();
+    assert_eq!( peaks.iter().map( |p| p.bin ).collect::<Vec<_>>(), vec![ 3 ] );
+}
+
+#[test]
+fn test_peaks_two_sided() {
+    //complex exponential in a two-sided spectrum, found in both orders
+    let ( n, fs ) = ( 64, 64f64 );
+    let bins = ( 0..n ).map( |i| complex::Complex::from_polar( &0.5, &( -2. * ::std::f64::consts::PI * 10.25 * i as f64 / fs ) ) )
+        .collect::<Vec<_>>();
+    let spectrum = Spectrum::new( ::fft::fft_complex( &bins[..] ), fs );
+    let options = PeakOptions { interpolation: Interpolation::Jacobsen, ..PeakOptions::default() };
+    let peaks = find_peaks( &spectrum, &options ).unwrap();
+    assert_eq!( peaks.len(), 1 );
+    assert!( nearly_equal( &peaks[0].frequency, &-10.25, &0.01 ) );
+    assert!( nearly_equal( &peaks[0].amplitude, &0.5, &0.01 ) );
+    let centered = find_peaks( &spectrum.fftshift().unwrap(), &options ).unwrap();
+    assert!( nearly_equal( &centered[0].frequency, &peaks[0].frequency, &1e-9 ) );
+    assert!( find_peaks( &Spectrum::new( vec![ complex::Complex::new( 1f64, 0. ); 2 ], fs ), &options ).is_err() );
+}
+
+#[test]
+fn test_peaks_non_finite_bins() {
+    let ( n, fs ) = ( 256, 256. );
+    let signal = tones( n, fs, &[ ( 1.0, 40. ), ( 0.5, 100. ) ] );
+    let windowed = Window::Hann.apply( &signal[..], Symmetry::Periodic ).unwrap();
+    let mut bins = Spectrum::rfft( &windowed[..], fs ).bins().to_vec();
+    bins[70] = complex::Complex::new( f64::NAN, 0. );
+    bins[90] = complex::Complex::new( f64::INFINITY, 0. );
+    bins[91] = bins[90];
+    let spectrum = Spectrum::from_rfft( bins, n, fs ).unwrap();
+    for &interpolation in [ Interpolation::None, Interpolation::Parabolic, Interpolation::Gaussian,
+                            Interpolation::Quinn, Interpolation::Jacobsen ].iter() {
+        let options = PeakOptions { interpolation, window: Window::Hann, threshold_db: -20., ..PeakOptions::default() };
+        let peaks = find_peaks( &spectrum, &options ).unwrap();
+        assert_eq!( peaks.iter().map( |p| p.bin ).collect::<Vec<_>>(), vec![ 40, 100 ], "{:?}", interpolation );
+    }
+}
@@ -0,0 +1,178 @@
+///Power spectral density estimation by periodogram and Welch's averaged periodograms
+use error;
+use fft;
+use fft::FftFloat;
+use spectrum;
+pub use spectrum::Sides;
+use window::{ Symmetry, Window };
+
+#[cfg(test)]
+use test_common::{ nearly_equal, noise };
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Detrend {
+    None,
+    ///remove the mean
+    Constant,
+    ///remove a least squares line
+    Linear,
+}
+
+///density in units^2/Hz at each frequency in Hz
+#[derive(Debug, Clone)]
+pub struct Psd<T> {
+    pub freqs: Vec<T>,
+    pub density: Vec<T>,
+}
+
+fn detrend<T: FftFloat>( arr: &[T], mode: Detrend ) -> Vec<T> {
+    let n = T::from( arr.len() ).unwrap();
+    match mode {
+        Detrend::None => arr.to_vec(),
+        Detrend::Constant => {
+            let mean = arr.iter().fold( T::zero(), |acc, x| acc + *x ) / n;
+            arr.iter().map( |x| *x - mean ).collect()
+        },
+        Detrend::Linear => {
+            let t_mean = ( n - T::one() ) / T::from( 2 ).unwrap();
+            let x_mean = arr.iter().fold( T::zero(), |acc, x| acc + *x ) / n;
+            let ( num, den ) = arr.iter().enumerate().fold( ( T::zero(), T::zero() ), |acc, (i, x)| {
+                let dt = T::from( i ).unwrap() - t_mean;
+                ( acc.0 + dt * ( *x - x_mean ), acc.1 + dt * dt )
+            });
+            let slope = if den > T::zero() { num / den } else { T::zero() };
+            arr.iter().enumerate()
+                .map( |(i, x)| *x - x_mean - slope * ( T::from( i ).unwrap() - t_mean ) ).collect()
+        },
+    }
+}
+
+fn frequencies<T: FftFloat>( n: usize, fs: T, sides: Sides ) -> Vec<T> {
+    match sides {
+        Sides::OneSided => spectrum::rfftfreq( n, fs ),
+        Sides::TwoSided => spectrum::fftfreq( n, fs ),
+    }
+}
+
+///|X_k|^2 / (fs * sum w^2) of one windowed segment, folded for one-sided output
+fn segment_density<T: FftFloat>( segment: &[T], coeff: &[T], fs: T, mode: Detrend, sides: Sides ) -> Vec<T> {
+    let x = detrend( segment, mode ).iter().zip( coeff.iter() ).map( |(x, w)| *x * *w ).collect::<Vec<_>>();
+    let scale = T::one() / ( fs * coeff.iter().fold( T::zero(), |acc, w| acc + *w * *w ) );
+    let n = segment.len();
+    match sides {
+        Sides::TwoSided => fft::fft_dif( &x[..] ).iter().map( |v| v.norm_sqr() * scale ).collect(),
+        Sides::OneSided => {
+            let two = T::from( 2 ).unwrap();
+            fft::rfft( &x[..] ).iter().enumerate().map( |(k, v)| {
+                //dc and nyquist have no negative frequency counterpart
+                if k == 0 || ( n % 2 == 0 && k == n / 2 ) {
+                    v.norm_sqr() * scale
+                } else {
+                    v.norm_sqr() * scale * two
+                }
+            }).collect()
+        },
+    }
+}
+
+///single windowed periodogram over the whole signal
+pub fn periodogram<T: FftFloat>( signal: &[T], fs: T, window: Window, detrend: Detrend, sides: Sides ) -> Result< Psd<T>, error::Error > {
+    if signal.is_empty() {
+        return Err( error::Error::DataEmpty )
+    }
+    if fs <= T::zero() {
+        return Err( error::Error::DataInvalid )
+    }
+    let coeff = window.coefficients::<T>( signal.len(), Symmetry::Periodic )?;
+    Ok( Psd {
+        freqs: frequencies( signal.len(), fs, sides ),
+        density: segment_density( signal, &coeff[..], fs, detrend, sides ),
+    })
+}
+
+///average of periodograms over segments of segment_len overlapping by overlap samples
+pub fn psd_welch<T: FftFloat>( signal: &[T], fs: T, segment_len: usize, overlap: usize, window: Window, detrend: Detrend, sides: Sides ) -> Result< Psd<T>, error::Error > {
+    if segment_len == 0 || overlap >= segment_len || fs <= T::zero() {
+        return Err( error::Error::DataInvalid )
+    }
+    if signal.len() < segment_len {
+        return Err( error::Error::DataInsufficient )
+    }
+    let step = segment_len - overlap;
+    let coeff = window.coefficients::<T>( segment_len, Symmetry::Periodic )?;
+    let num_segments = 1 + ( signal.len() - segment_len ) / step;
+    let mut density = vec![];
+    for i in 0..num_segments {
+        let start = i * step;
+        let d = segment_density( &signal[ start .. start + segment_len ], &coeff[..], fs, detrend, sides );
+        if density.is_empty() {
+            density = d;
+        } else {
+            density.iter_mut().zip( d.iter() ).for_each( |(acc, x)| *acc += *x );
+        }
+    }
+    let count = T::from( num_segments ).unwrap();
+    Ok( Psd {
+        freqs: frequencies( segment_len, fs, sides ),
+        density: density.iter().map( |x| *x / count ).collect(),
+    })
+}
+
+#[test]
+fn test_periodogram_parseval() {
+    let fs = 250.;
+    let signal = noise( 500, 7 );
+    let mean_sq = signal.iter().map( |x| x * x ).sum::<f64>() / signal.len() as f64;
+    for &sides in [ Sides::OneSided, Sides::TwoSided ].iter() {
+        let p = periodogram( &signal[..], fs, Window::Rectangular, Detrend::None, sides ).expect("periodogram failed");
+        let df = fs / signal.len() as f64;
+        let total = p.density.iter().sum::<f64>() * df;
+        assert!( nearly_equal( &total, &mean_sq, &1e-9 ) );
+    }
+    let p = periodogram( &signal[..], fs, Window::Rectangular, Detrend::None, Sides::TwoSided ).expect("periodogram failed");
+    assert_eq!( p.freqs.len(), 500 );
+    assert!( nearly_equal( &p.freqs[1], &0.5, &1e-12 ) );
+    assert!( nearly_equal( &p.freqs[499], &-0.5, &1e-12 ) );
+    assert!( nearly_equal( &p.freqs[250], &-125., &1e-12 ) );
+}
+
+#[test]
+fn test_welch_tone_and_noise() {
+    let fs = 1000.;
+    let amplitude = 3.;
+    let signal = (0..20000).map( |i| amplitude * ( 2. * ::std::f64::consts::PI * 125. * i as f64 / fs ).sin() )
+        .collect::<Vec<_>>();
+    let p = psd_welch( &signal[..], fs, 256, 128, Window::Hann, Detrend::Constant, Sides::OneSided ).expect("welch failed");
+    assert_eq!( p.freqs.len(), 129 );
+    let peak = p.density.iter().enumerate().fold( 0, |acc, (k, x)| if *x > p.density[acc] { k } else { acc } );
+    assert!( nearly_equal( &p.freqs[peak], &125., &1e-9 ) );
+    //integrated density recovers the tone power A^2/2
+    let total = p.density.iter().sum::<f64>() * fs / 256.;
+    assert!( nearly_equal( &total, &( amplitude * amplitude / 2. ), &1e-6 ) );
+
+    //uniform white noise has variance 1/3 spread over fs/2
+    let signal = noise( 200000, 3 );
+    let p = psd_welch( &signal[..], fs, 512, 256, Window::Hann, Detrend::None, Sides::OneSided ).expect("welch failed");
+    let level = 2. / 3. / fs;
+    let mean = p.density[1..256].iter().sum::<f64>() / 255.;
+    assert!( nearly_equal( &mean, &level, &( 0.02 * level ) ) );
+}
+
+#[test]
+fn test_detrend() {
+    let signal = (0..64).map( |i| 5. + 0.25 * i as f64 ).collect::<Vec<_>>();
+    assert!( detrend( &signal[..], Detrend::Linear ).iter().all( |x| nearly_equal( x, &0., &1e-12 ) ) );
+    let d = detrend( &signal[..], Detrend::Constant );
+    assert!( nearly_equal( &d.iter().sum::<f64>(), &0., &1e-9 ) );
+    let p = periodogram( &signal[..], 1., Window::Hann, Detrend::Linear, Sides::OneSided ).expect("periodogram failed");
+    assert!( p.density.iter().all( |x| *x < 1e-20 ) );
+}
+
+#[test]
+fn test_welch_invalid() {
+    let signal = noise( 100, 1 );
+    assert!( psd_welch( &signal[..], 1., 128, 64, Window::Hann, Detrend::None, Sides::OneSided ).is_err() );
+    assert!( psd_welch( &signal[..], 1., 64, 64, Window::Hann, Detrend::None, Sides::OneSided ).is_err() );
+    assert!( psd_welch( &signal[..], 0., 64, 32, Window::Hann, Detrend::None, Sides::OneSided ).is_err() );
+    assert!( periodogram::<f64>( &[], 1., Window::Hann, Detrend::None, Sides::OneSided ).is_err() );
+}
@@ -0,0 +1,319 @@
+///Spectra that carry their sample rate: bin frequencies, fftshift ordering, magnitude and phase views
+extern crate num;
+
+use self::num::complex;
+
+use error;
+use fft;
+use fft::{ FftFloat, Order };
+use utility;
+
+#[cfg(test)]
+use test_common::nearly_equal;
+
+///power in decibels, 10*log10(|X|^2), clamped below at floor_db
+pub fn power_db<T: FftFloat>( bins: &[complex::Complex<T>], floor_db: T ) -> Vec<T> {
+    let ten = T::from( 10. ).unwrap();
+    bins.iter().map( |x| {
+        let p = x.norm_sqr();
+        if p > T::zero() {
+            ( ten * p.log10() ).max( floor_db )
+        } else {
+            floor_db
+        }
+    }).collect()
+}
+
+///bin frequencies of an n point transform in fft order, negative frequencies in the upper half
+pub fn fftfreq<T: FftFloat>( n: usize, fs: T ) -> Vec<T> {
+    let df = fs / T::from( n ).unwrap();
+    (0..n).map( |k| {
+        if k < ( n + 1 ) / 2 {
+            T::from( k ).unwrap() * df
+        } else {
+            -T::from( n - k ).unwrap() * df
+        }
+    }).collect()
+}
+
+///bin frequencies 0..=fs/2 of the n/2+1 bins rfft returns for an n point signal
+pub fn rfftfreq<T: FftFloat>( n: usize, fs: T ) -> Vec<T> {
+    let df = fs / T::from( n ).unwrap();
+    (0..n/2+1).map( |k| T::from( k ).unwrap() * df ).collect()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Sides {
+    ///bins 0..=n/2 with the power of negative frequencies folded in
+    OneSided,
+    ///all n bins in fft order, negative frequencies in the upper half
+    TwoSided,
+}
+
+///transform bins together with the sample rate and signal length they came from
+#[derive(Debug, Clone)]
+pub struct Spectrum<T: FftFloat> {
+    bins: Vec<complex::Complex<T>>,
+    fs: T,
+    n: usize,
+    sides: Sides,
+    order: Order,
+}
+
+impl<T: FftFloat> Spectrum<T> {
+
+    ///full n bin spectrum in fft order, as from fft::fft_complex
+    pub fn new( bins: Vec<complex::Complex<T>>, fs: T ) -> Spectrum<T> {
+        Spectrum {
+            n: bins.len(),
+            bins,
+            fs,
+            sides: Sides::TwoSided,
+            order: Order::Natural,
+        }
+    }
+
+    ///one-sided spectrum of an n point real signal, as from fft::rfft
+    pub fn from_rfft( bins: Vec<complex::Complex<T>>, n: usize, fs: T ) -> Result< Spectrum<T>, error::Error > {
+        if bins.len() != n / 2 + 1 {
+            return Err( error::Error::Dimension )
+        }
+        Ok( Spectrum {
+            bins,
+            fs,
+            n,
+            sides: Sides::OneSided,
+            order: Order::Natural,
+        } )
+    }
+
+    ///full spectrum of a real signal
+    pub fn fft( signal: &[T], fs: T ) -> Spectrum<T> {
+        Spectrum::new( fft::fft_dif( signal ), fs )
+    }
+
+    ///one-sided spectrum of a real signal
+    pub fn rfft( signal: &[T], fs: T ) -> Spectrum<T> {
+        Spectrum {
+            bins: fft::rfft( signal ),
+            fs,
+            n: signal.len(),
+            sides: Sides::OneSided,
+            order: Order::Natural,
+        }
+    }
+
+    pub fn bins( &self ) -> &[complex::Complex<T>] {
+        &self.bins[..]
+    }
+
+    pub fn into_bins( self ) -> Vec<complex::Complex<T>> {
+        self.bins
+    }
+
+    pub fn len( &self ) -> usize {
+        self.bins.len()
+    }
+
+    pub fn is_empty( &self ) -> bool {
+        self.bins.is_empty()
+    }
+
+    pub fn sample_rate( &self ) -> T {
+        self.fs
+    }
+
+    ///length of the time domain signal, differs from len() for one-sided spectra
+    pub fn signal_len( &self ) -> usize {
+        self.n
+    }
+
+    pub fn sides( &self ) -> Sides {
+        self.sides
+    }
+
+    pub fn order( &self ) -> Order {
+        self.order
+    }
+
+    ///bin spacing fs/n in Hz
+    pub fn resolution( &self ) -> T {
+        self.fs / T::from( self.n ).unwrap()
+    }
+
+    ///frequency in Hz of every bin in the current order
+    pub fn freqs( &self ) -> Vec<T> {
+        match ( self.sides, self.order ) {
+            ( Sides::OneSided, _ ) => rfftfreq( self.n, self.fs ),
+            ( Sides::TwoSided, Order::Natural ) => fftfreq( self.n, self.fs ),
+            ( Sides::TwoSided, Order::Centered ) => fft::fftshift( &fftfreq( self.n, self.fs )[..] ),
+        }
+    }
+
+    ///frequency in Hz of bin index
+    pub fn bin_freq( &self, index: usize ) -> Option<T> {
+        if index >= self.bins.len() {
+            return None
+        }
+        let k = match ( self.sides, self.order ) {
+            ( Sides::OneSided, _ ) => index as isize,
+            ( Sides::TwoSided, Order::Natural ) => {
+                if index < ( self.n + 1 ) / 2 { index as isize } else { index as isize - self.n as isize }
+            },
+            ( Sides::TwoSided, Order::Centered ) => index as isize - ( self.n / 2 ) as isize,
+        };
+        Some( T::from( k ).unwrap() * self.resolution() )
+    }
+
+    ///index of the bin nearest to hz, None beyond the frequencies the spectrum holds.
+    ///+fs/2 and -fs/2 both map to the nyquist bin of even length two-sided spectra
+    pub fn nearest_bin( &self, hz: T ) -> Option<usize> {
+        if self.n == 0 {
+            return None
+        }
+        let k = ( hz / self.resolution() ).round().to_isize()?;
+        let ( n, half ) = ( self.n as isize, ( self.n / 2 ) as isize );
+        match ( self.sides, self.order ) {
+            ( Sides::OneSided, _ ) => if k >= 0 && k <= half { Some( k as usize ) } else { None },
+            ( Sides::TwoSided, order ) => {
+                if k < -half || k > half {
+                    return None
+                }
+                let natural = k.rem_euclid( n ) as usize;
+                match order {
+                    Order::Natural => Some( natural ),
+                    Order::Centered => Some( ( natural + self.n / 2 ) % self.n ),
+                }
+            },
+        }
+    }
+
+    ///bin nearest to hz
+    pub fn at( &self, hz: T ) -> Option<complex::Complex<T>> {
+        self.nearest_bin( hz ).map( |i| self.bins[i] )
+    }
+
+    ///copy with the zero frequency bin moved to the middle, unchanged if already centered.
+    ///one-sided spectra have no negative frequencies to move and give Error::Operation
+    pub fn fftshift( &self ) -> Result< Spectrum<T>, error::Error > {
+        self.reorder( Order::Centered )
+    }
+
+    ///copy back in fft order, unchanged if already in it
+    pub fn ifftshift( &self ) -> Result< Spectrum<T>, error::Error > {
+        self.reorder( Order::Natural )
+    }
+
+    fn reorder( &self, order: Order ) -> Result< Spectrum<T>, error::Error > {
+        if self.sides == Sides::OneSided {
+            return Err( error::Error::Operation )
+        }
+        let bins = match ( self.order, order ) {
+            ( Order::Natural, Order::Centered ) => fft::fftshift( &self.bins[..] ),
+            ( Order::Centered, Order::Natural ) => fft::ifftshift( &self.bins[..] ),
+            _ => self.bins.clone(),
+        };
+        Ok( Spectrum { bins, order, ..*self } )
+    }
+
+    pub fn magnitude( &self ) -> Vec<T> {
+        self.bins.iter().map( |x| x.norm() ).collect()
+    }
+
+    pub fn power( &self ) -> Vec<T> {
+        self.bins.iter().map( |x| x.norm_sqr() ).collect()
+    }
+
+    ///power in decibels, 10*log10(|X|^2), clamped below at floor_db
+    pub fn db( &self, floor_db: T ) -> Vec<T> {
+        power_db( &self.bins[..], floor_db )
+    }
+
+    ///wrapped phase in (-pi, pi]
+    pub fn phase( &self ) -> Vec<T> {
+        self.bins.iter().map( |x| x.arg() ).collect()
+    }
+
+    ///phase with 2pi jumps between adjacent bins removed, in the current bin order
+    pub fn unwrapped_phase( &self ) -> Vec<T> {
+        utility::unwrap_phase( &self.phase()[..] )
+    }
+}
+
+#[test]
+fn test_fftfreq() {
+    let f = fftfreq( 8, 8f32 );
+    assert_eq!( f, vec![ 0., 1., 2., 3., -4., -3., -2., -1. ] );
+    let f = fftfreq( 5, 10f32 );
+    assert_eq!( f, vec![ 0., 2., 4., -4., -2. ] );
+    assert_eq!( rfftfreq( 8, 8f32 ), vec![ 0., 1., 2., 3., 4. ] );
+    assert_eq!( rfftfreq( 5, 10f32 ), vec![ 0., 2., 4. ] );
+}
+
+#[test]
+fn test_spectrum_nearest_bin() {
+    //tone at 125 Hz, 1000 Hz sampling and 64 points gives 15.625 Hz bins
+    let fs = 1000f64;
+    let signal = (0..64).map( |i| ( 2. * ::std::f64::consts::PI * 125. * i as f64 / fs ).cos() ).collect::<Vec<_>>();
+    let full = Spectrum::fft( &signal[..], fs );
+    assert!( nearly_equal( &full.resolution(), &15.625, &1e-12 ) );
+    assert_eq!( full.nearest_bin( 125. ), Some( 8 ) );
+    assert_eq!( full.nearest_bin( -125. ), Some( 56 ) );
+    assert_eq!( full.nearest_bin( 130. ), Some( 8 ) );
+    assert_eq!( full.nearest_bin( 500. ), Some( 32 ) );
+    assert_eq!( full.nearest_bin( -500. ), Some( 32 ) );
+    assert_eq!( full.nearest_bin( 510. ), None );
+    assert!( nearly_equal( &full.at( 125. ).unwrap().re, &32., &1e-9 ) );
+
+    let centered = full.fftshift().unwrap();
+    assert_eq!( centered.order(), Order::Centered );
+    assert_eq!( centered.nearest_bin( 0. ), Some( 32 ) );
+    assert_eq!( centered.nearest_bin( 125. ), Some( 40 ) );
+    assert_eq!( centered.nearest_bin( -125. ), Some( 24 ) );
+    assert!( nearly_equal( &centered.bin_freq( 24 ).unwrap(), &-125., &1e-9 ) );
+    for (i, f) in centered.freqs().iter().enumerate() {
+        assert_eq!( centered.bin_freq( i ), Some( *f ) );
+        assert_eq!( centered.nearest_bin( *f ), Some( i ) );
+    }
+    let back = centered.ifftshift().unwrap();
+    assert_eq!( back.bins(), full.bins() );
+
+    let half = Spectrum::rfft( &signal[..], fs );
+    assert_eq!( half.len(), 33 );
+    assert_eq!( half.signal_len(), 64 );
+    assert_eq!( half.nearest_bin( 125. ), Some( 8 ) );
+    assert_eq!( half.nearest_bin( -125. ), None );
+    assert!( half.fftshift().is_err() );
+    half.bins().iter().zip( full.bins().iter() )
+        .for_each( |x| assert!( nearly_equal( &x.0.re, &x.1.re, &1e-9 ) && nearly_equal( &x.0.im, &x.1.im, &1e-9 ) ) );
+
+    //odd lengths have no nyquist bin
+    let odd = Spectrum::fft( &signal[..63], fs );
+    for (i, f) in odd.freqs().iter().enumerate() {
+        assert_eq!( odd.nearest_bin( *f ), Some( i ) );
+    }
+    assert!( Spectrum::from_rfft( vec![ complex::Complex::new( 0f64, 0. ); 4 ], 8, fs ).is_err() );
+}
+
+#[test]
+fn test_spectrum_views() {
+    let bins = vec![ complex::Complex::new( 3f32, 4. ), complex::Complex::new( 0., 0. ), complex::Complex::new( 0., -2. ) ];
+    let s = Spectrum::new( bins, 3f32 );
+    assert_eq!( s.magnitude(), vec![ 5., 0., 2. ] );
+    assert_eq!( s.power(), vec![ 25., 0., 4. ] );
+    let db = s.db( -100. );
+    assert!( nearly_equal( &db[0], &( 10. * 25f32.log10() ), &1e-5 ) );
+    assert_eq!( db[1], -100. );
+    assert!( nearly_equal( &s.phase()[2], &( -::std::f32::consts::FRAC_PI_2 ), &1e-6 ) );
+
+    //linear phase of a delayed impulse unwraps to a straight line
+    let n = 32;
+    let mut impulse = vec![ 0f64; n ];
+    impulse[5] = 1.;
+    let s = Spectrum::rfft( &impulse[..], 1. );
+    let unwrapped = s.unwrapped_phase();
+    for (k, p) in unwrapped.iter().enumerate() {
+        let expect = -2. * ::std::f64::consts::PI * 5. * k as f64 / n as f64;
+        assert!( nearly_equal( p, &expect, &1e-9 ) );
+    }
+}
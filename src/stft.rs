@@ -0,0 +1,194 @@
+///Short-time Fourier transform of real signals, frames are one-sided spectra from fft::rfft
+extern crate num;
+
+use self::num::complex;
+use ndarray::Array2;
+
+use error;
+use fft;
+use fft::FftFloat;
+use spectrum;
+use window::{ Symmetry, Window };
+
+#[cfg(test)]
+use test_common::nearly_equal;
+
+///number of whole frames of frame_len advancing by hop that fit in a signal of length len
+pub fn num_frames( len: usize, frame_len: usize, hop: usize ) -> usize {
+    if len < frame_len || hop == 0 {
+        0
+    } else {
+        1 + ( len - frame_len ) / hop
+    }
+}
+
+///returns a matrix of frames x (frame_len/2+1) bins, frame i starts at sample i*hop
+pub fn stft<T: FftFloat>( signal: &[T], window: Window, frame_len: usize, hop: usize ) -> Result< Array2<complex::Complex<T>>, error::Error > {
+    if frame_len == 0 || hop == 0 {
+        return Err( error::Error::DataInvalid )
+    }
+    if signal.len() < frame_len {
+        return Err( error::Error::DataInsufficient )
+    }
+    let coeff = window.coefficients::<T>( frame_len, Symmetry::Periodic )?;
+    let frames = num_frames( signal.len(), frame_len, hop );
+    let bins = frame_len / 2 + 1;
+    let mut ret = Array2::from_elem( ( frames, bins ), complex::Complex::new( T::zero(), T::zero() ) );
+    let mut frame = vec![ T::zero(); frame_len ];
+    for (i, mut row) in ret.outer_iter_mut().enumerate() {
+        let start = i * hop;
+        for (j, x) in frame.iter_mut().enumerate() {
+            *x = signal[ start + j ] * coeff[j];
+        }
+        let spectrum = fft::rfft( &frame[..] );
+        for (dst, src) in row.iter_mut().zip( spectrum.iter() ) {
+            *dst = *src;
+        }
+    }
+    Ok( ret )
+}
+
+///per hop offset sums of w^p over all frames overlapping a sample in steady state
+fn overlap_sums<T: FftFloat>( coeff: &[T], hop: usize, p: i32 ) -> Vec<T> {
+    (0..hop).map( |r| {
+        coeff.iter().skip( r ).step_by( hop ).fold( T::zero(), |acc, w| acc + w.powi( p ) )
+    }).collect()
+}
+
+///constant overlap-add: sum_m w(n - m*hop) is the same for every n
+pub fn check_cola<T: FftFloat>( window: Window, frame_len: usize, hop: usize, tol: T ) -> bool {
+    if frame_len == 0 || hop == 0 || hop > frame_len {
+        return false
+    }
+    let coeff = match window.coefficients::<T>( frame_len, Symmetry::Periodic ) {
+        Ok( x ) => x,
+        Err( _ ) => return false,
+    };
+    let sums = overlap_sums( &coeff[..], hop, 1 );
+    let mean = sums.iter().fold( T::zero(), |acc, x| acc + *x ) / T::from( hop ).unwrap();
+    mean > T::zero() && sums.iter().all( |x| ( *x - mean ).abs() <= tol * mean )
+}
+
+///nonzero overlap-add: sum_m w^2(n - m*hop) is nonzero for every n, needed for istft
+pub fn check_nola<T: FftFloat>( window: Window, frame_len: usize, hop: usize, tol: T ) -> bool {
+    if frame_len == 0 || hop == 0 || hop > frame_len {
+        return false
+    }
+    match window.coefficients::<T>( frame_len, Symmetry::Periodic ) {
+        Ok( coeff ) => overlap_sums( &coeff[..], hop, 2 ).iter().all( |x| *x > tol ),
+        Err( _ ) => false,
+    }
+}
+
+///inverse of stft by weighted overlap-add: y(n) = sum_m w(n-m*hop) y_m(n-m*hop) / sum_m w^2(n-m*hop).
+///returns (frames-1)*hop+frame_len samples
+pub fn istft<T: FftFloat>( spec: &Array2<complex::Complex<T>>, window: Window, frame_len: usize, hop: usize ) -> Result< Vec<T>, error::Error > {
+    let ( frames, bins ) = spec.dim();
+    if frame_len == 0 || bins != frame_len / 2 + 1 {
+        return Err( error::Error::Dimension )
+    }
+    let eps = T::epsilon();
+    if !check_nola( window, frame_len, hop, eps ) {
+        return Err( error::Error::DataInvalid )
+    }
+    if frames == 0 {
+        return Ok( vec![] )
+    }
+    let coeff = window.coefficients::<T>( frame_len, Symmetry::Periodic )?;
+    let len = ( frames - 1 ) * hop + frame_len;
+    let mut ret = vec![ T::zero(); len ];
+    let mut norm = vec![ T::zero(); len ];
+    for (i, row) in spec.outer_iter().enumerate() {
+        let frame = fft::irfft( &row.to_vec()[..], frame_len )?;
+        let start = i * hop;
+        for (j, x) in frame.iter().enumerate() {
+            ret[ start + j ] += *x * coeff[j];
+            norm[ start + j ] += coeff[j] * coeff[j];
+        }
+    }
+    //samples not covered by any nonzero window weight are left at zero
+    for (x, w) in ret.iter_mut().zip( norm.iter() ) {
+        *x = if *w > eps { *x / *w } else { T::zero() };
+    }
+    Ok( ret )
+}
+
+pub fn magnitude<T: FftFloat>( spec: &Array2<complex::Complex<T>> ) -> Array2<T> {
+    spec.mapv( |x| x.norm() )
+}
+
+pub fn power<T: FftFloat>( spec: &Array2<complex::Complex<T>> ) -> Array2<T> {
+    spec.mapv( |x| x.norm_sqr() )
+}
+
+///power in decibels, 10*log10(|X|^2), clamped below at floor_db
+pub fn power_db<T: FftFloat>( spec: &Array2<complex::Complex<T>>, floor_db: T ) -> Array2<T> {
+    let db = match spec.as_slice() {
+        Some( bins ) => spectrum::power_db( bins, floor_db ),
+        None => spectrum::power_db( &spec.iter().cloned().collect::<Vec<_>>()[..], floor_db ),
+    };
+    Array2::from_shape_vec( spec.dim(), db ).unwrap()
+}
+
+#[test]
+fn test_stft_tone() {
+    //tone centered on bin 8 of a 64 point frame
+    let frame_len = 64;
+    let signal = (0..1000).map( |i| ( 2. * ::std::f64::consts::PI * 8. * i as f64 / frame_len as f64 ).sin() )
+        .collect::<Vec<_>>();
+    let spec = stft( &signal[..], Window::Hann, frame_len, 16 ).expect("stft failed");
+    assert_eq!( spec.dim(), ( num_frames( 1000, frame_len, 16 ), frame_len / 2 + 1 ) );
+    assert_eq!( spec.dim().0, 59 );
+    let mag = magnitude( &spec );
+    for row in mag.outer_iter() {
+        let peak = row.iter().enumerate().fold( 0, |acc, (k, x)| if *x > row[acc] { k } else { acc } );
+        assert_eq!( peak, 8 );
+        //hann coherent gain of 0.5 on a unit sine
+        assert!( nearly_equal( &row[8], &( 0.5 * frame_len as f64 / 2. ), &1e-9 ) );
+    }
+    let p = power( &spec );
+    let db = power_db( &spec, -200. );
+    assert!( nearly_equal( &db[[0,8]], &( 10. * p[[0,8]].log10() ), &1e-9 ) );
+    assert!( db.iter().all( |x| *x >= -200. ) );
+}
+
+#[test]
+fn test_stft_invalid() {
+    let signal = [ 0f32; 10 ];
+    assert!( stft( &signal[..], Window::Hann, 16, 4 ).is_err() );
+    assert!( stft( &signal[..], Window::Hann, 8, 0 ).is_err() );
+    assert!( stft( &signal[..], Window::Hann, 0, 4 ).is_err() );
+}
+
+#[test]
+fn test_cola_nola() {
+    assert!( check_cola( Window::Hann, 64, 32, 1e-9f64 ) );
+    assert!( check_cola( Window::Hann, 64, 16, 1e-9f64 ) );
+    assert!( check_cola( Window::Hamming, 64, 32, 1e-9f64 ) );
+    assert!( check_cola( Window::Rectangular, 64, 64, 1e-9f64 ) );
+    assert!( !check_cola( Window::Hann, 64, 48, 1e-9f64 ) );
+    assert!( check_nola( Window::Hann, 64, 48, 1e-9f64 ) );
+    assert!( !check_nola( Window::Hann, 64, 64, 1e-9f64 ) );
+    assert!( !check_nola( Window::Rectangular, 64, 65, 1e-9f64 ) );
+}
+
+#[test]
+fn test_istft_round_trip() {
+    let signal = (0..1040).map( |i| ( i as f64 * 0.05 ).sin() + 0.3 * ( i as f64 * 0.71 ).cos() ).collect::<Vec<_>>();
+    for &( window, hop ) in [ ( Window::Hann, 16 ), ( Window::Hann, 48 ), ( Window::Hamming, 32 ), ( Window::Rectangular, 64 ) ].iter() {
+        let spec = stft( &signal[..], window, 64, hop ).expect("stft failed");
+        let out = istft( &spec, window, 64, hop ).expect("istft failed");
+        assert_eq!( out.len(), ( spec.dim().0 - 1 ) * hop + 64 );
+        //hann starts at zero so the first sample is not recoverable
+        signal.iter().zip( out.iter() ).skip( 1 )
+            .for_each( |x| assert!( nearly_equal( x.0, x.1, &1e-9 ) ) );
+    }
+}
+
+#[test]
+fn test_istft_invalid() {
+    let signal = vec![ 1f64; 256 ];
+    let spec = stft( &signal[..], Window::Hann, 64, 64 ).expect("stft failed");
+    assert!( istft( &spec, Window::Hann, 64, 64 ).is_err() );
+    assert!( istft( &spec, Window::Hann, 32, 16 ).is_err() );
+}
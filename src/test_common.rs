@@ -1,3 +1,7 @@
+extern crate num;
+
+use self::num::complex;
+use self::num::Float;
 use std::ops::Sub;
 use std::fmt::Debug;
 
@@ -13,3 +17,37 @@ pub fn nearly_equal<'a, T>( aa: &'a T, bb: & 'a T, ee: & 'a T ) -> bool
     let e1 = ee.clone();
     dd < e1       
 }
+
+///states of the linear congruential generator the tests draw reproducible data from
+pub fn lcg( seed: u64 ) -> impl Iterator<Item = u64> {
+    let mut state = seed;
+    ::std::iter::repeat( () ).map( move |_| {
+        state = state.wrapping_mul( 6364136223846793005 ).wrapping_add( 1442695040888963407 );
+        state
+    })
+}
+
+///uniform on [0,1)
+pub fn uniform( n: usize, seed: u64 ) -> Vec<f64> {
+    lcg( seed ).take( n ).map( |s| ( s >> 11 ) as f64 / ( 1u64 << 53 ) as f64 ).collect()
+}
+
+///uniform on [-1,1)
+pub fn noise( n: usize, seed: u64 ) -> Vec<f64> {
+    uniform( n, seed ).iter().map( |x| x * 2. - 1. ).collect()
+}
+
+///real and imaginary parts uniform on [-1,1)
+pub fn noise_complex( n: usize, seed: u64 ) -> Vec<complex::Complex<f64>> {
+    noise( 2 * n, seed ).chunks( 2 ).map( |x| complex::Complex::new( x[0], x[1] ) ).collect()
+}
+
+///deterministic real signal with no symmetry for transform tests
+pub fn test_signal<T: Float>( n: usize ) -> Vec<T> {
+    (0..n).map( |i| T::from( ( i as f64 * 0.37 ).sin() + ( ( i * i ) % 7 ) as f64 * 0.1 ).unwrap() ).collect()
+}
+
+pub fn assert_slice_eq( a: &[f64], b: &[f64], tol: f64 ) {
+    assert_eq!( a.len(), b.len() );
+    a.iter().zip( b.iter() ).for_each( |x| assert!( nearly_equal( x.0, x.1, &tol ), "{:?} != {:?}", a, b ) );
+}
@@ -1,3 +1,9 @@
+extern crate chrono;
+extern crate num;
+
+use self::chrono::{ DateTime, TimeZone };
+use self::num::traits::{ Float, FloatConst };
+
 pub fn calc_mean_from_raw( arr: &[f64] ) -> Option< f64 >{
     if arr.len() == 0 {
         None
@@ -30,6 +36,40 @@ pub fn calc_precision_weights( precisions: &[f64] ) -> Vec<f64> {
 }
 
 
+///removes 2pi jumps between consecutive phase samples
+pub fn unwrap_phase<T: Float + FloatConst>( phase: &[T] ) -> Vec<T> {
+    let two_pi = T::PI() + T::PI();
+    let mut offset = T::zero();
+    let mut ret = Vec::with_capacity( phase.len() );
+    for (i, p) in phase.iter().enumerate() {
+        if i > 0 {
+            let d = *p - phase[ i - 1 ];
+            if d > T::PI() {
+                offset = offset - ( two_pi * ( ( d - T::PI() ) / two_pi ).floor() + two_pi );
+            } else if d < -T::PI() {
+                offset = offset + two_pi * ( ( -d - T::PI() ) / two_pi ).floor() + two_pi;
+            }
+        }
+        ret.push( *p + offset );
+    }
+    ret
+}
+
+///seconds of each timestamp after the first, for irregularly sampled series
+pub fn elapsed_seconds<Tz: TimeZone>( times: &[DateTime<Tz>] ) -> Vec<f64> {
+    match times.first() {
+        Some( t0 ) => times.iter().map( |t| {
+            let d = t.clone().signed_duration_since( t0.clone() );
+            //nanoseconds overflow past ~292 years, where millisecond precision is plenty
+            match d.num_nanoseconds() {
+                Some( ns ) => ns as f64 * 1e-9,
+                _ => d.num_milliseconds() as f64 * 1e-3,
+            }
+        }).collect(),
+        _ => vec![],
+    }
+}
+
 #[test]
 fn test_mean(){
     let x = &[ 0.0, 3.5, 99.9, -45.0 ];
@@ -54,3 +94,31 @@ fn test_calc_mean_variance_precision_from_raw(){
     assert!( n.2 < 1./3721.5 + ERROR );
     assert!( n.2 > 1./3721.5 - ERROR );
 }
+
+#[test]
+fn test_unwrap_phase(){
+    use std::f64::consts::PI;
+    const ERROR : f64 = 1e-12;
+    let ramp = (0..50).map( |i| i as f64 * 0.9 ).collect::<Vec<_>>();
+    let wrapped = ramp.iter().map( |x| x.sin().atan2( x.cos() ) ).collect::<Vec<_>>();
+    let unwrapped = unwrap_phase( &wrapped );
+    ramp.iter().zip( unwrapped.iter() ).for_each( |(&x0,&x1)| assert!( x0 < x1 + ERROR &&
+                                                                      x0 > x1 - ERROR ) );
+    //jumps of several turns collapse to the nearest equivalent step
+    let unwrapped = unwrap_phase( &[ 0., 0.5 + 6. * PI, 1. - 4. * PI ] );
+    let expected = [ 0., 0.5, 1. ];
+    unwrapped.iter().zip( expected.iter() ).for_each( |(&x0,&x1)| assert!( x0 < x1 + ERROR &&
+                                                                          x0 > x1 - ERROR ) );
+}
+
+#[test]
+fn test_elapsed_seconds() {
+    use self::chrono::{ Duration, Utc };
+    let t0 = "2017-07-14T02:40:00Z".parse::<DateTime<Utc>>().unwrap();
+    let times = [ t0, t0 + Duration::milliseconds( 250 ), t0 + Duration::seconds( 3 ) + Duration::nanoseconds( 5 ) ];
+    let s = elapsed_seconds( &times[..] );
+    assert_eq!( s[0], 0. );
+    assert!( ( s[1] - 0.25 ).abs() < 1e-12 );
+    assert!( ( s[2] - 3.000000005 ).abs() < 1e-12 );
+    assert!( elapsed_seconds::<Utc>( &[] ).is_empty() );
+}
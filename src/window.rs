@@ -0,0 +1,292 @@
+///Tapering windows with coherent gain and equivalent noise bandwidth
+extern crate num;
+
+use self::num::complex;
+
+use error;
+use fft;
+use fft::FftFloat;
+
+#[cfg(test)]
+use test_common::{ nearly_equal, assert_slice_eq };
+
+use std::f64::consts::PI;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Window {
+    Rectangular,
+    Hann,
+    Hamming,
+    Blackman,
+    ///4-term Blackman-Harris
+    BlackmanHarris,
+    ///5-term flat-top for amplitude accuracy
+    FlatTop,
+    ///Kaiser-Bessel with shape parameter beta
+    Kaiser( f64 ),
+    ///tapered cosine, alpha is the tapered fraction of the window, 0 gives Rectangular and 1 gives Hann
+    Tukey( f64 ),
+    ///Gaussian with standard deviation in samples
+    Gaussian( f64 ),
+    ///Dolph-Chebyshev with sidelobe attenuation in dB
+    DolphChebyshev( f64 ),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Symmetry {
+    ///w(n) = w(N-1-n), for filter design
+    Symmetric,
+    ///one period of an N-periodic sequence, for spectral analysis
+    Periodic,
+}
+
+///modified Bessel function of the first kind of order 0
+pub(crate) fn bessel_i0( x: f64 ) -> f64 {
+    let half = x / 2.;
+    let mut term = 1.;
+    let mut sum = 1.;
+    let mut k = 1.;
+    while term > 1e-17 * sum {
+        term *= ( half / k ) * ( half / k );
+        sum += term;
+        k += 1.;
+    }
+    sum
+}
+
+fn cosine_sum( a: &[f64], n: usize, len: usize ) -> f64 {
+    let x = 2. * PI * n as f64 / ( len - 1 ) as f64;
+    a.iter().enumerate().fold( 0., |acc, (k, ak)| {
+        let sign = if k % 2 == 0 { 1. } else { -1. };
+        acc + sign * ak * ( k as f64 * x ).cos()
+    })
+}
+
+fn chebyshev( len: usize, attenuation_db: f64 ) -> Vec<f64> {
+    //evaluate the Chebyshev polynomial on the unit circle and transform back to time
+    let order = ( len - 1 ) as f64;
+    let beta = ( ( 10f64.powf( attenuation_db.abs() / 20. ) ).acosh() / order ).cosh();
+    let p = (0..len).map( |k| {
+        let x = beta * ( PI * k as f64 / len as f64 ).cos();
+        let v = if x > 1. {
+            ( order * x.acosh() ).cosh()
+        } else if x < -1. {
+            let sign = if len % 2 == 1 { 1. } else { -1. };
+            sign * ( order * ( -x ).acosh() ).cosh()
+        } else {
+            ( order * x.acos() ).cos()
+        };
+        if len % 2 == 1 {
+            complex::Complex::new( v, 0. )
+        } else {
+            complex::Complex::from_polar( &v, &( PI * k as f64 / len as f64 ) )
+        }
+    }).collect::<Vec<_>>();
+    let w = fft::fft_complex( &p[..] ).iter().map( |x| x.re ).collect::<Vec<_>>();
+    let mut ret = if len % 2 == 1 {
+        let half = ( len + 1 ) / 2;
+        let mut v = w[1..half].iter().rev().cloned().collect::<Vec<_>>();
+        v.extend_from_slice( &w[..half] );
+        v
+    } else {
+        let half = len / 2 + 1;
+        let mut v = w[1..half].iter().rev().cloned().collect::<Vec<_>>();
+        v.extend_from_slice( &w[1..half] );
+        v
+    };
+    let max = ret.iter().cloned().fold( 0., f64::max );
+    for x in ret.iter_mut() {
+        *x /= max;
+    }
+    ret
+}
+
+fn symmetric( window: Window, len: usize ) -> Vec<f64> {
+    if len == 1 {
+        return vec![ 1. ]
+    }
+    let m = ( len - 1 ) as f64;
+    match window {
+        Window::Rectangular => vec![ 1.; len ],
+        Window::Hann => (0..len).map( |n| cosine_sum( &[ 0.5, 0.5 ], n, len ) ).collect(),
+        Window::Hamming => (0..len).map( |n| cosine_sum( &[ 0.54, 0.46 ], n, len ) ).collect(),
+        Window::Blackman => (0..len).map( |n| cosine_sum( &[ 0.42, 0.5, 0.08 ], n, len ) ).collect(),
+        Window::BlackmanHarris => {
+            (0..len).map( |n| cosine_sum( &[ 0.35875, 0.48829, 0.14128, 0.01168 ], n, len ) ).collect()
+        },
+        Window::FlatTop => {
+            let a = [ 0.21557895, 0.41663158, 0.277263158, 0.083578947, 0.006947368 ];
+            (0..len).map( |n| cosine_sum( &a, n, len ) ).collect()
+        },
+        Window::Kaiser( beta ) => {
+            let denom = bessel_i0( beta );
+            (0..len).map( |n| {
+                let r = 2. * n as f64 / m - 1.;
+                bessel_i0( beta * ( 1. - r * r ).max( 0. ).sqrt() ) / denom
+            }).collect()
+        },
+        Window::Tukey( alpha ) => {
+            if alpha <= 0. {
+                return vec![ 1.; len ]
+            }
+            let alpha = alpha.min( 1. );
+            let edge = alpha * m / 2.;
+            (0..len).map( |n| {
+                let d = ( n as f64 ).min( m - n as f64 );
+                if d < edge {
+                    0.5 * ( 1. - ( PI * d / edge ).cos() )
+                } else {
+                    1.
+                }
+            }).collect()
+        },
+        Window::Gaussian( sigma ) => {
+            (0..len).map( |n| {
+                let d = ( n as f64 - m / 2. ) / sigma;
+                ( -0.5 * d * d ).exp()
+            }).collect()
+        },
+        Window::DolphChebyshev( attenuation_db ) => chebyshev( len, attenuation_db ),
+    }
+}
+
+impl Window {
+
+    ///errors with DataInvalid for a Gaussian whose sigma is not positive
+    pub fn coefficients<T: FftFloat>( &self, len: usize, symmetry: Symmetry ) -> Result< Vec<T>, error::Error > {
+        if let Window::Gaussian( sigma ) = *self {
+            if sigma.is_nan() || sigma <= 0. {
+                return Err( error::Error::DataInvalid )
+            }
+        }
+        let w = match ( len, symmetry ) {
+            ( 0, _ ) => vec![],
+            ( _, Symmetry::Symmetric ) => symmetric( *self, len ),
+            ( _, Symmetry::Periodic ) => {
+                let mut w = symmetric( *self, len + 1 );
+                w.truncate( len );
+                w
+            },
+        };
+        Ok( w.iter().map( |x| T::from( *x ).unwrap() ).collect() )
+    }
+
+    ///tapers the signal, the window length follows the signal length
+    pub fn apply<T: FftFloat>( &self, signal: &[T], symmetry: Symmetry ) -> Result< Vec<T>, error::Error > {
+        Ok( self.coefficients::<T>( signal.len(), symmetry )?.iter().zip( signal.iter() )
+            .map( |(w, x)| *w * *x ).collect() )
+    }
+
+    pub fn coherent_gain<T: FftFloat>( &self, len: usize, symmetry: Symmetry ) -> Result< T, error::Error > {
+        coherent_gain( &self.coefficients::<T>( len, symmetry )?[..] )
+    }
+
+    pub fn enbw<T: FftFloat>( &self, len: usize, symmetry: Symmetry ) -> Result< T, error::Error > {
+        enbw( &self.coefficients::<T>( len, symmetry )?[..] )
+    }
+}
+
+///mean of the window, the amplitude scaling applied to a bin-centered tone
+pub fn coherent_gain<T: FftFloat>( coeff: &[T] ) -> Result< T, error::Error > {
+    if coeff.is_empty() {
+        return Err( error::Error::DataEmpty )
+    }
+    let sum = coeff.iter().fold( T::zero(), |acc, x| acc + *x );
+    Ok( sum / T::from( coeff.len() ).unwrap() )
+}
+
+///equivalent noise bandwidth in bins, N * sum(w^2) / sum(w)^2
+pub fn enbw<T: FftFloat>( coeff: &[T] ) -> Result< T, error::Error > {
+    if coeff.is_empty() {
+        return Err( error::Error::DataEmpty )
+    }
+    let sum = coeff.iter().fold( T::zero(), |acc, x| acc + *x );
+    let sum_sq = coeff.iter().fold( T::zero(), |acc, x| acc + *x * *x );
+    Ok( T::from( coeff.len() ).unwrap() * sum_sq / ( sum * sum ) )
+}
+
+#[test]
+fn test_window_values() {
+    assert_slice_eq( &Window::Hann.coefficients::<f64>( 5, Symmetry::Symmetric ).unwrap(), &[ 0., 0.5, 1., 0.5, 0. ], 1e-12 );
+    assert_slice_eq( &Window::Hann.coefficients::<f64>( 4, Symmetry::Periodic ).unwrap(), &[ 0., 0.5, 1., 0.5 ], 1e-12 );
+    assert_slice_eq( &Window::Hamming.coefficients::<f64>( 4, Symmetry::Periodic ).unwrap(), &[ 0.08, 0.54, 1., 0.54 ], 1e-12 );
+    assert_slice_eq( &Window::Blackman.coefficients::<f64>( 3, Symmetry::Symmetric ).unwrap(), &[ 0., 1., 0. ], 1e-12 );
+    assert_slice_eq( &Window::BlackmanHarris.coefficients::<f64>( 3, Symmetry::Symmetric ).unwrap(), &[ 6e-5, 1., 6e-5 ], 1e-12 );
+    assert_slice_eq( &Window::Kaiser( 0. ).coefficients::<f64>( 4, Symmetry::Symmetric ).unwrap(), &[ 1.; 4 ], 1e-12 );
+    assert_slice_eq( &Window::Tukey( 0. ).coefficients::<f64>( 6, Symmetry::Symmetric ).unwrap(), &[ 1.; 6 ], 1e-12 );
+    assert_slice_eq( &Window::Tukey( 1. ).coefficients::<f64>( 9, Symmetry::Symmetric ).unwrap(),
+                     &Window::Hann.coefficients::<f64>( 9, Symmetry::Symmetric ).unwrap(), 1e-12 );
+    assert_slice_eq( &Window::Tukey( 0.5 ).coefficients::<f64>( 5, Symmetry::Symmetric ).unwrap(), &[ 0., 1., 1., 1., 0. ], 1e-12 );
+    let g = Window::Gaussian( 1. ).coefficients::<f64>( 3, Symmetry::Symmetric ).unwrap();
+    assert_slice_eq( &g, &[ ( -0.5f64 ).exp(), 1., ( -0.5f64 ).exp() ], 1e-12 );
+    let k = Window::Kaiser( 5. ).coefficients::<f64>( 3, Symmetry::Symmetric ).unwrap();
+    assert_slice_eq( &k, &[ 1. / bessel_i0( 5. ), 1., 1. / bessel_i0( 5. ) ], 1e-12 );
+    assert!( Window::FlatTop.coefficients::<f32>( 0, Symmetry::Periodic ).unwrap().is_empty() );
+    assert!( Window::Gaussian( 0. ).coefficients::<f64>( 4, Symmetry::Symmetric ).is_err() );
+    assert!( Window::Gaussian( -1. ).apply( &[ 1f64; 4 ], Symmetry::Periodic ).is_err() );
+}
+
+#[test]
+fn test_window_symmetry() {
+    let all = [ Window::Rectangular, Window::Hann, Window::Hamming, Window::Blackman, Window::BlackmanHarris,
+                Window::FlatTop, Window::Kaiser( 8.6 ), Window::Tukey( 0.25 ), Window::Gaussian( 7. ),
+                Window::DolphChebyshev( 80. ) ];
+    for w in all.iter() {
+        for &len in [ 31, 32 ].iter() {
+            let c = w.coefficients::<f64>( len, Symmetry::Symmetric ).unwrap();
+            assert_eq!( c.len(), len );
+            for n in 0..len {
+                assert!( nearly_equal( &c[n], &c[len - 1 - n], &1e-9 ), "{:?} not symmetric", w );
+            }
+            //odd lengths sample the peak exactly
+            let max = c.iter().cloned().fold( 0., f64::max );
+            assert!( max <= 1. + 1e-6, "{:?} peak {}", w, max );
+            if len % 2 == 1 {
+                assert!( nearly_equal( &max, &1., &1e-6 ), "{:?} peak {}", w, max );
+            }
+            let p = w.coefficients::<f64>( len, Symmetry::Periodic ).unwrap();
+            assert_slice_eq( &p, &w.coefficients::<f64>( len + 1, Symmetry::Symmetric ).unwrap()[..len], 1e-12 );
+        }
+    }
+}
+
+#[test]
+fn test_window_gain_enbw() {
+    let n = 4096;
+    let expected = [ ( Window::Rectangular, 1., 1. ),
+                     ( Window::Hann, 0.5, 1.5 ),
+                     ( Window::Hamming, 0.54, 1.3628 ),
+                     ( Window::Blackman, 0.42, 1.7268 ),
+                     ( Window::BlackmanHarris, 0.35875, 2.0044 ),
+                     ( Window::FlatTop, 0.21557895, 3.7702 ) ];
+    for &( w, gain, bw ) in expected.iter() {
+        assert!( nearly_equal( &w.coherent_gain::<f64>( n, Symmetry::Periodic ).unwrap(), &gain, &1e-6 ), "{:?}", w );
+        assert!( nearly_equal( &w.enbw::<f64>( n, Symmetry::Periodic ).unwrap(), &bw, &1e-3 ), "{:?}", w );
+    }
+    assert!( Window::Hann.coherent_gain::<f64>( 0, Symmetry::Periodic ).is_err() );
+    assert!( Window::Hann.enbw::<f64>( 0, Symmetry::Periodic ).is_err() );
+    assert!( coherent_gain::<f32>( &[] ).is_err() && enbw::<f32>( &[] ).is_err() );
+    let signal = [ 2f32; 8 ];
+    let tapered = Window::Hann.apply( &signal[..], Symmetry::Periodic ).unwrap();
+    assert!( nearly_equal( &tapered[4], &2., &1e-6 ) && nearly_equal( &tapered[0], &0., &1e-6 ) );
+}
+
+#[test]
+fn test_chebyshev_sidelobes() {
+    //all sidelobes sit at the requested attenuation below the mainlobe
+    for &len in [ 31, 32 ].iter() {
+        let c = Window::DolphChebyshev( 60. ).coefficients::<f64>( len, Symmetry::Symmetric ).unwrap();
+        let mut padded = c.iter().map( |x| complex::Complex::new( *x, 0. ) ).collect::<Vec<_>>();
+        padded.resize( 4096, complex::Complex::new( 0., 0. ) );
+        let spectrum = fft::fft_complex( &padded[..] ).iter().map( |x| x.norm() ).collect::<Vec<_>>();
+        let main = spectrum[0];
+        //skip the mainlobe up to its first null
+        let mut k = 1;
+        while spectrum[k+1] < spectrum[k] {
+            k += 1;
+        }
+        let sidelobe = spectrum[k..2048].iter().cloned().fold( 0., f64::max );
+        let db = 20. * ( sidelobe / main ).log10();
+        assert!( nearly_equal( &db, &-60., &0.1 ), "sidelobe {} dB", db );
+    }
+}